@@ -0,0 +1,15 @@
+CREATE TABLE tus_upload (
+                            id INTEGER PRIMARY KEY AUTOINCREMENT,
+                            upload_id TEXT NOT NULL UNIQUE,
+                            file_name TEXT NOT NULL,
+                            content_type TEXT,
+                            upload_length BIGINT NOT NULL,
+                            upload_offset BIGINT NOT NULL DEFAULT 0,
+                            owner_id INTEGER,
+                            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+
+                            FOREIGN KEY (owner_id) REFERENCES users(id)
+                                ON DELETE CASCADE
+                                ON UPDATE CASCADE
+);
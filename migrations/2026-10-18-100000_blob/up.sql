@@ -0,0 +1,15 @@
+CREATE TABLE blob (
+                      id INTEGER PRIMARY KEY AUTOINCREMENT,
+                      content_hash TEXT NOT NULL,         -- hex encoded digest
+                      hash_algorithm TEXT NOT NULL,       -- sha256, blake3 or legacy for rows from before content digests
+                      storage_path TEXT NOT NULL UNIQUE,
+                      size BIGINT NOT NULL,
+                      ref_count INTEGER NOT NULL DEFAULT 1,
+                      created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+
+                      UNIQUE (hash_algorithm, content_hash)
+);
+
+-- Older rows carry salted bcrypt hashes that cannot identify content, keep one blob per stored object for them.
+INSERT INTO blob (content_hash, hash_algorithm, storage_path, size, ref_count)
+SELECT MIN(content_hash), 'legacy', storage_path, MAX(size), COUNT(*) FROM file GROUP BY storage_path;
@@ -0,0 +1,12 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX file_to_link_file_id;
+DROP INDEX file_to_link_link;
+DELETE FROM file_to_link WHERE file_id IS NOT NULL;
+ALTER TABLE file_to_link DROP COLUMN created_at;
+ALTER TABLE file_to_link DROP COLUMN created_by;
+ALTER TABLE file_to_link DROP COLUMN revoked_at;
+ALTER TABLE file_to_link DROP COLUMN download_count;
+ALTER TABLE file_to_link DROP COLUMN max_downloads;
+ALTER TABLE file_to_link DROP COLUMN password_hash;
+ALTER TABLE file_to_link DROP COLUMN expires_at;
+ALTER TABLE file_to_link DROP COLUMN file_id;
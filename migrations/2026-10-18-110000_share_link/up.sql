@@ -0,0 +1,15 @@
+ALTER TABLE file_to_link ADD COLUMN file_id INTEGER NULL REFERENCES file(id) ON DELETE CASCADE;
+ALTER TABLE file_to_link ADD COLUMN expires_at DATETIME NULL;
+ALTER TABLE file_to_link ADD COLUMN password_hash TEXT NULL;
+ALTER TABLE file_to_link ADD COLUMN max_downloads INTEGER NULL;
+ALTER TABLE file_to_link ADD COLUMN download_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE file_to_link ADD COLUMN revoked_at DATETIME NULL;
+ALTER TABLE file_to_link ADD COLUMN created_by INTEGER NULL REFERENCES users(id) ON DELETE SET NULL;
+ALTER TABLE file_to_link ADD COLUMN created_at DATETIME NULL; -- SQLite does not allow CURRENT_TIMESTAMP defaults on added columns
+
+CREATE UNIQUE INDEX file_to_link_link ON file_to_link(link);
+CREATE INDEX file_to_link_file_id ON file_to_link(file_id);
+
+-- Keep every link handed out so far working as an unrestricted share link
+INSERT INTO file_to_link (link, filename, file_id, created_by, created_at)
+SELECT hashed_file_name, file_name, id, owner_id, created_at FROM file;
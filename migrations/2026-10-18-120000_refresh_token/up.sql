@@ -0,0 +1,15 @@
+CREATE TABLE refresh_token (
+                      id INTEGER PRIMARY KEY AUTOINCREMENT,
+                      user_id INTEGER NOT NULL,
+                      token_hash TEXT NOT NULL UNIQUE,    -- hex encoded SHA-256 of the token, the token itself is never stored
+                      family_id TEXT NOT NULL,            -- shared by every token rotated from the same login
+                      expires_at DATETIME NOT NULL,
+                      created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                      used_at DATETIME NULL,              -- set once the token was exchanged, a second use is a replay
+                      revoked_at DATETIME NULL,
+
+                      FOREIGN KEY (user_id) REFERENCES users(id)
+                          ON DELETE CASCADE
+);
+
+CREATE INDEX refresh_token_family_id ON refresh_token(family_id);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE users DROP COLUMN tokens_valid_after;
+drop table revoked_token;
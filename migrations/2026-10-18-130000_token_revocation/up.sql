@@ -0,0 +1,12 @@
+CREATE TABLE revoked_token (
+                      jti TEXT PRIMARY KEY NOT NULL,
+                      user_id INTEGER NOT NULL,
+                      expires_at DATETIME NOT NULL,      -- the entry is pointless once the token itself expired
+                      revoked_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+
+                      FOREIGN KEY (user_id) REFERENCES users(id)
+                          ON DELETE CASCADE
+);
+
+-- Access tokens issued at or before this time are rejected, set by revoke-all
+ALTER TABLE users ADD COLUMN tokens_valid_after DATETIME NULL;
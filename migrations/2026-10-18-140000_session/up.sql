@@ -0,0 +1,16 @@
+CREATE TABLE session (
+                      id INTEGER PRIMARY KEY AUTOINCREMENT,
+                      user_id INTEGER NOT NULL,
+                      family_id TEXT NOT NULL UNIQUE,    -- refresh token family of this login
+                      user_agent TEXT NULL,
+                      ip_address TEXT NULL,
+                      created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                      last_seen_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                      expires_at DATETIME NOT NULL,      -- follows the newest refresh token
+                      revoked_at DATETIME NULL,
+
+                      FOREIGN KEY (user_id) REFERENCES users(id)
+                          ON DELETE CASCADE
+);
+
+CREATE INDEX session_user_id ON session(user_id);
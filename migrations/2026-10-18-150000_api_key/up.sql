@@ -0,0 +1,17 @@
+CREATE TABLE api_key (
+                      id INTEGER PRIMARY KEY AUTOINCREMENT,
+                      user_id INTEGER NOT NULL,
+                      name TEXT NOT NULL,
+                      prefix TEXT NOT NULL,              -- first characters of the key, shown in listings
+                      key_hash TEXT NOT NULL UNIQUE,     -- hex encoded SHA-256 of the key, the key itself is never stored
+                      scopes TEXT NOT NULL,              -- space separated
+                      created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                      last_used_at DATETIME NULL,
+                      expires_at DATETIME NULL,
+                      revoked_at DATETIME NULL,
+
+                      FOREIGN KEY (user_id) REFERENCES users(id)
+                          ON DELETE CASCADE
+);
+
+CREATE INDEX api_key_user_id ON api_key(user_id);
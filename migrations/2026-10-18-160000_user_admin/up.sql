@@ -0,0 +1,2 @@
+-- Admins additionally get the `admin` scope on login, granted by setting this to 1
+ALTER TABLE users ADD COLUMN is_admin INTEGER NOT NULL DEFAULT 0;
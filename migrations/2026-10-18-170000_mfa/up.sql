@@ -0,0 +1,22 @@
+CREATE TABLE user_mfa (
+                      user_id INTEGER PRIMARY KEY NOT NULL,
+                      totp_secret TEXT NOT NULL,         -- base32, needed in clear to compute codes
+                      confirmed_at DATETIME NULL,        -- login only asks for a code once enrollment was confirmed
+                      last_used_step BIGINT NULL,        -- time step of the last accepted code, a code works once
+                      created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+
+                      FOREIGN KEY (user_id) REFERENCES users(id)
+                          ON DELETE CASCADE
+);
+
+CREATE TABLE mfa_recovery_code (
+                      id INTEGER PRIMARY KEY AUTOINCREMENT,
+                      user_id INTEGER NOT NULL,
+                      code_hash TEXT NOT NULL,           -- hex encoded SHA-256 of the normalized code
+                      used_at DATETIME NULL,
+
+                      FOREIGN KEY (user_id) REFERENCES users(id)
+                          ON DELETE CASCADE
+);
+
+CREATE INDEX mfa_recovery_code_user_id ON mfa_recovery_code(user_id);
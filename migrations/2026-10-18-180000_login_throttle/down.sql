@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+drop table login_lockout;
+drop table login_throttle;
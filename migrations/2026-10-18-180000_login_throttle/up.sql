@@ -0,0 +1,18 @@
+CREATE TABLE login_throttle (
+                      throttle_key TEXT PRIMARY KEY NOT NULL,  -- `account:<name>` or `ip:<address>`
+                      failures INTEGER NOT NULL,
+                      last_failure_at DATETIME NOT NULL,
+                      blocked_until DATETIME NULL              -- backoff or lockout, attempts before it are refused
+);
+
+-- Audit trail, one row per lockout
+CREATE TABLE login_lockout (
+                      id INTEGER PRIMARY KEY AUTOINCREMENT,
+                      throttle_key TEXT NOT NULL,
+                      ip_address TEXT NULL,                    -- address of the attempt that triggered the lockout
+                      failures INTEGER NOT NULL,
+                      locked_until DATETIME NOT NULL,
+                      created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX login_lockout_throttle_key ON login_lockout(throttle_key);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+-- The renamed accounts keep their new names and emails, the originals can not be told apart anymore.
+SELECT 1;
@@ -0,0 +1,20 @@
+-- Names and emails that only differ in case would stop the case-insensitive unique indexes of
+-- user_login_identifiers from being created. The oldest account keeps its value, newer ones get their id
+-- appended: `name-<id>` and `local+duplicate-<id>@domain`, which mail servers deliver to the same mailbox.
+UPDATE users SET name = name || '-' || id
+WHERE EXISTS (
+    SELECT 1 FROM users AS older
+    WHERE lower(older.name) = lower(users.name)
+      AND older.id < users.id
+);
+
+UPDATE users SET email = CASE
+        WHEN instr(email, '@') > 0
+            THEN substr(email, 1, instr(email, '@') - 1) || '+duplicate-' || id || substr(email, instr(email, '@'))
+        ELSE email || '+duplicate-' || id
+    END
+WHERE EXISTS (
+    SELECT 1 FROM users AS older
+    WHERE lower(older.email) = lower(users.email)
+      AND older.id < users.id
+);
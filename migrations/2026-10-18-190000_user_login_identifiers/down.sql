@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX users_email_nocase;
+DROP INDEX users_name_nocase;
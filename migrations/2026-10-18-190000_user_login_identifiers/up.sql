@@ -0,0 +1,4 @@
+-- Logins look users up by name or email ignoring case, so both have to be unique that way.
+-- Databases with duplicate names need them renamed before this applies.
+CREATE UNIQUE INDEX users_name_nocase ON users(lower(name));
+CREATE UNIQUE INDEX users_email_nocase ON users(lower(email));
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX file_folder_id;
+ALTER TABLE file DROP COLUMN folder_id;
+DROP INDEX folder_parent_id;
+DROP INDEX folder_sibling_name;
+DROP TABLE folder;
@@ -0,0 +1,22 @@
+CREATE TABLE folder (
+                      id INTEGER PRIMARY KEY AUTOINCREMENT,
+                      owner_id INTEGER NOT NULL,
+                      parent_id INTEGER NULL,            -- NULL for folders at the top level
+                      name TEXT NOT NULL,
+                      path TEXT NOT NULL,                -- cached `/parent/child` path, rewritten on rename and move
+                      created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                      updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+
+                      FOREIGN KEY (owner_id) REFERENCES users(id)
+                          ON DELETE CASCADE,
+                      FOREIGN KEY (parent_id) REFERENCES folder(id)
+                          ON DELETE CASCADE
+);
+
+-- Siblings need distinct names, top level folders included (NULL parents would never clash otherwise)
+CREATE UNIQUE INDEX folder_sibling_name ON folder(owner_id, IFNULL(parent_id, 0), name);
+CREATE INDEX folder_parent_id ON folder(parent_id);
+
+ALTER TABLE file ADD COLUMN folder_id INTEGER NULL REFERENCES folder(id) ON DELETE SET NULL;
+
+CREATE INDEX file_folder_id ON file(folder_id);
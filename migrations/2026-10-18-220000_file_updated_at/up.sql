@@ -0,0 +1,8 @@
+-- SQLite has no ON UPDATE CURRENT_TIMESTAMP. Only changes to the file itself count, moving it into the
+-- trash and back leaves `updated_at` alone. Recursive triggers are off, the inner UPDATE does not fire again.
+CREATE TRIGGER file_updated_at
+    AFTER UPDATE OF file_name, folder_id, content_hash, content_type, size, storage_path, is_public ON file
+    FOR EACH ROW WHEN NEW.updated_at IS OLD.updated_at
+BEGIN
+    UPDATE file SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
@@ -0,0 +1,15 @@
+-- Names only have to be unique among the live files of one owner's folder. Clashes from before (tus uploads
+-- were never checked) keep the name on the oldest file, the others get their id appended.
+UPDATE file SET file_name = file_name || ' (' || id || ')'
+WHERE (is_deleted IS NULL OR is_deleted != 1)
+  AND EXISTS (
+      SELECT 1 FROM file AS older
+      WHERE older.owner_id IS file.owner_id
+        AND IFNULL(older.folder_id, 0) = IFNULL(file.folder_id, 0)
+        AND older.file_name = file.file_name
+        AND (older.is_deleted IS NULL OR older.is_deleted != 1)
+        AND older.id < file.id
+  );
+
+CREATE UNIQUE INDEX file_sibling_name ON file(owner_id, IFNULL(folder_id, 0), file_name)
+    WHERE is_deleted IS NULL OR is_deleted != 1;
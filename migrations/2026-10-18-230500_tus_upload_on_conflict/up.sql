@@ -0,0 +1,2 @@
+-- What a finished tus upload does when its name is taken, see ConflictPolicy
+ALTER TABLE tus_upload ADD COLUMN on_conflict TEXT NOT NULL DEFAULT 'reject';
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE file DROP COLUMN version;
+DROP INDEX file_version_replaced_at;
+DROP TABLE file_version;
@@ -0,0 +1,20 @@
+-- The file row always holds the current content, earlier contents move here when a new version replaces them.
+-- Each row keeps its blob reference until the version is pruned or the file is purged.
+CREATE TABLE file_version (
+                      id INTEGER PRIMARY KEY AUTOINCREMENT,
+                      file_id INTEGER NOT NULL,
+                      version INTEGER NOT NULL,          -- counts up per file, the current one is file.version
+                      content_hash TEXT NOT NULL,
+                      content_type TEXT NOT NULL,
+                      size BIGINT NOT NULL,
+                      storage_path TEXT NOT NULL,
+                      replaced_at DATETIME DEFAULT CURRENT_TIMESTAMP, -- retention by age counts from here
+
+                      FOREIGN KEY (file_id) REFERENCES file(id)
+                          ON DELETE CASCADE,
+                      UNIQUE (file_id, version)
+);
+
+CREATE INDEX file_version_replaced_at ON file_version(replaced_at);
+
+ALTER TABLE file ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,2 @@
+-- Folder a finished tus upload is registered into, NULL for the top level
+ALTER TABLE tus_upload ADD COLUMN folder_id INTEGER NULL REFERENCES folder(id) ON DELETE SET NULL;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+UPDATE file SET storage_path = 'content/' || storage_path WHERE storage_path IN (SELECT storage_path FROM blob WHERE hash_algorithm = 'legacy');
+UPDATE blob SET storage_path = 'content/' || storage_path WHERE hash_algorithm = 'legacy';
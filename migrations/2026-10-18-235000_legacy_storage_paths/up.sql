@@ -0,0 +1,4 @@
+-- Files uploaded before the storage backend stored their path including the local root (`content/<name>`),
+-- storage keys are relative to that root now
+UPDATE file SET storage_path = substr(storage_path, 9) WHERE storage_path LIKE 'content/%';
+UPDATE blob SET storage_path = substr(storage_path, 9) WHERE storage_path LIKE 'content/%' AND hash_algorithm = 'legacy';
@@ -0,0 +1,17 @@
+-- Every accepted PATCH body, so finishing an upload reads exactly the parts that moved the offset and not the
+-- leftovers of requests that lost a race for the same offset.
+CREATE TABLE tus_upload_part (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    upload_id TEXT NOT NULL,
+    part_offset BIGINT NOT NULL,
+    part_key TEXT NOT NULL,
+    size BIGINT NOT NULL,
+
+    UNIQUE (upload_id, part_offset),
+    FOREIGN KEY (upload_id) REFERENCES tus_upload(upload_id)
+        ON DELETE CASCADE
+);
+
+-- Parts stored before this were never recorded, unfinished uploads start over from the beginning. Their old
+-- parts stay under the upload's prefix and are removed with the upload.
+UPDATE tus_upload SET upload_offset = 0;
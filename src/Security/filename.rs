@@ -0,0 +1,48 @@
+use unicode_normalization::UnicodeNormalization;
+
+/// Longest stored name in bytes, the limit of common file systems the file may be saved to.
+const MAX_FILE_NAME_BYTES: usize = 255;
+
+/// Characters that change how a name is displayed without showing up themselves. Bidi overrides let
+/// `invoice\u{202E}fdp.exe` render as `invoiceexe.pdf`, zero width ones make equal looking names differ.
+fn is_invisible(c: char) -> bool {
+    matches!(c, '\u{00AD}' | '\u{200B}'..='\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2060}'..='\u{2064}' | '\u{2066}'..='\u{2069}' | '\u{FEFF}')
+}
+
+/// The extension including its dot. Names without one and dot files like `.env` have none.
+pub fn file_extension(name: &str) -> Option<&str> {
+    match name.rfind('.') {
+        Some(dot) if dot > 0 => Some(&name[dot..]),
+        _ => None,
+    }
+}
+
+/// Shortens the part before the extension, so `report.pdf` stays a pdf however long its name is.
+fn truncate_keeping_extension(name: &str, max_bytes: usize) -> String {
+    if name.len() <= max_bytes {
+        return name.to_string()
+    }
+    let extension = file_extension(name).filter(|extension| extension.len() <= max_bytes / 2).unwrap_or("");
+    let stem = &name[..name.len() - extension.len()];
+    let mut end = max_bytes - extension.len();
+    while !stem.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}{}", stem[..end].trim_end(), extension)
+}
+
+/// Turns a client supplied file name into a single NFC normalized path segment without control or
+/// invisible characters. Directories are dropped, the extension is kept. `None` when nothing usable is left.
+pub fn sanitize_file_name(raw: &str) -> Option<String> {
+    // Some clients send the full local path, on Windows with backslashes.
+    let base = raw.rsplit(['/', '\\']).next().unwrap_or(raw);
+    let cleaned: String = base.nfc()
+        .filter(|c| !c.is_control() && !is_invisible(*c))
+        .collect();
+    // Windows drops trailing dots and spaces on save, this also turns `.` and `..` into nothing.
+    let cleaned = cleaned.trim().trim_end_matches(['.', ' ']);
+    if cleaned.is_empty() {
+        return None
+    }
+    Some(truncate_keeping_extension(cleaned, MAX_FILE_NAME_BYTES))
+}
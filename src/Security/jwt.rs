@@ -1,40 +1,169 @@
 
 use std::env;
 
-use axum::{http, Error};
+use axum::http;
 use axum::body::Body;
-use axum::http::{HeaderValue, Response, StatusCode};
-use axum::extract::Request;
+use axum::http::{Response, StatusCode};
+use axum::extract::{Request, State};
 use axum::middleware::Next;
+use chrono::Utc;
 use dotenv::dotenv;
 use jsonwebtoken::{encode, Algorithm, EncodingKey, Header, Validation, decode, DecodingKey, TokenData};
-use crate::model::securitymodel::{AuthError, EncodeJWT};
+use crate::model::securitymodel::{AuthError, AuthUser, EncodeJWT, PurposeClaims, Scope};
 use crate::model::securitymodel::AuthError::*;
 use crate::model::usermodel::ConversionError;
-use crate::repository::userrepository::check_if_user_exist;
+use crate::repository::sessionrepository::{get_session, touch_session};
+use crate::repository::userrepository::get_user_by_id;
+use crate::Security::revocation::is_token_revoked;
+use crate::service::apikeyservice::resolve_api_key;
 
-pub fn encode_jwt(name: &str, email: &str) -> Result<String, ConversionError>{
+/// Audiences of the single purpose tokens, see `encode_purpose_token`.
+const MFA_CHALLENGE_AUDIENCE: &str = "mfa";
+pub const EMAIL_VERIFICATION_AUDIENCE: &str = "verify-email";
+pub const PASSWORD_RESET_AUDIENCE: &str = "password-reset";
 
+/// Issuer written to and required from every token, read from `JWT_ISSUER` (defaults to `fileshare`).
+pub fn jwt_issuer() -> String {
+    dotenv().ok();
+    env::var("JWT_ISSUER").unwrap_or_else(|_| "fileshare".to_string())
+}
+
+/// Lifetime of access tokens in seconds, read from `ACCESS_TOKEN_TTL` (defaults to 15 minutes).
+pub fn access_token_ttl() -> i64 {
+    dotenv().ok();
+    env::var("ACCESS_TOKEN_TTL")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|ttl| *ttl > 0)
+        .unwrap_or(15 * 60)
+}
+
+pub fn encode_jwt(user_id: i32, name: &str, email: &str, session_id: Option<i32>, scopes: &[Scope]) -> Result<String, ConversionError>{
+
+    let issued_at = Utc::now().timestamp();
     let jwt_info = EncodeJWT {
+        sub: user_id.to_string(),
         username: name.to_string(),
-        email: email.to_string()
+        email: email.to_string(),
+        iss: jwt_issuer(),
+        iat: issued_at,
+        exp: issued_at + access_token_ttl(),
+        jti: uuid::Uuid::new_v4().simple().to_string(),
+        sid: session_id,
+        scope: Some(Scope::join(scopes)),
     };
 
     dotenv().ok();
     let secret = env::var("JWT_SECRET")?;
-    let token = encode(&Header::default(), &jwt_info, &EncodingKey::from_secret(secret.as_ref())).unwrap();
+    let token = encode(&Header::default(), &jwt_info, &EncodingKey::from_secret(secret.as_ref()))
+        .map_err(|e| ConversionError::ConversionError(format!("Error encoding JWT: {}", e)))?;
     Ok(token)
 }
 
+/// Lifetime of MFA challenge tokens in seconds, read from `MFA_CHALLENGE_TTL` (defaults to 5 minutes).
+pub fn mfa_challenge_ttl() -> i64 {
+    dotenv().ok();
+    env::var("MFA_CHALLENGE_TTL")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|ttl| *ttl > 0)
+        .unwrap_or(5 * 60)
+}
+
+/// Signs a single purpose token for `user_id`. The audience names the purpose, so the token is useless
+/// anywhere else; access token validation rejects every token with an audience.
+pub fn encode_purpose_token(user_id: i32, audience: &str, ttl: i64, fingerprint: Option<String>) -> Result<String, ConversionError> {
+    let issued_at = Utc::now().timestamp();
+    let claims = PurposeClaims {
+        sub: user_id.to_string(),
+        aud: audience.to_string(),
+        iss: jwt_issuer(),
+        iat: issued_at,
+        exp: issued_at + ttl,
+        jti: uuid::Uuid::new_v4().simple().to_string(),
+        fpr: fingerprint,
+    };
+
+    dotenv().ok();
+    let secret = env::var("JWT_SECRET")?;
+    encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_ref()))
+        .map_err(|e| ConversionError::ConversionError(format!("Error encoding {} token: {}", audience, e)))
+}
+
+pub fn decode_purpose_token(token: &str, audience: &str) -> Result<PurposeClaims, ConversionError> {
+    dotenv().ok();
+    let secret = env::var("JWT_SECRET")?;
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.set_issuer(&[jwt_issuer()]);
+    validation.set_audience(&[audience]);
+    validation.set_required_spec_claims(&["exp", "iat", "iss", "sub", "aud"]);
+
+    decode::<PurposeClaims>(token, &DecodingKey::from_secret(secret.as_ref()), &validation)
+        .map(|data| data.claims)
+        .map_err(|e| ConversionError::Unauthorized(format!("Invalid {} token: {}", audience, e)))
+}
+
+pub fn encode_mfa_challenge(user_id: i32) -> Result<String, ConversionError> {
+    encode_purpose_token(user_id, MFA_CHALLENGE_AUDIENCE, mfa_challenge_ttl(), None)
+}
+
+pub fn decode_mfa_challenge(token: &str) -> Result<PurposeClaims, ConversionError> {
+    decode_purpose_token(token, MFA_CHALLENGE_AUDIENCE)
+}
+
+/// Checks signature, expiry and issuer. Any failure is reported as `Unauthorized`.
 pub fn decode_jwt(jwt_token: String)->  Result<TokenData<EncodeJWT>, ConversionError>{
 
     dotenv().ok();
     let secret = env::var("JWT_SECRET")?;
-    let token_message = decode::<EncodeJWT>(&jwt_token, &DecodingKey::from_secret(secret.as_ref()), &Validation::new(Algorithm::HS256)).unwrap();
-    Ok(token_message)
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.set_issuer(&[jwt_issuer()]);
+    validation.set_required_spec_claims(&["exp", "iat", "iss", "sub"]);
+
+    decode::<EncodeJWT>(&jwt_token, &DecodingKey::from_secret(secret.as_ref()), &validation)
+        .map_err(|e| ConversionError::Unauthorized(format!("Invalid JWT: {}", e)))
 }
 
-pub async fn authenticate(mut req:Request, next: Next ) -> Result<Response<Body>, AuthError>{
+async fn authenticate_jwt(token: &str) -> Result<AuthUser, AuthError> {
+    let token_data = decode_jwt(token.to_string())?;
+    let user_id = token_data.claims.sub.parse::<i32>()
+        .map_err(|_| AuthError("Invalid subject in JWT Token".to_string(), StatusCode::UNAUTHORIZED))?;
+    let user = get_user_by_id(user_id).await?
+        .ok_or_else(|| AuthError("User in JWT Token does not exist in Database".to_string(), StatusCode::FORBIDDEN))?;
+
+    let claims = token_data.claims;
+    if is_token_revoked(&claims.jti).await? {
+        return Err(AuthError("JWT Token was revoked".to_string(), StatusCode::UNAUTHORIZED))
+    }
+    // Seconds are all `iat` has, a token issued in the same second as a revoke-all counts as revoked.
+    if matches!(user.tokens_valid_after, Some(valid_after) if claims.iat <= valid_after.and_utc().timestamp()) {
+        return Err(AuthError("JWT Token was revoked".to_string(), StatusCode::UNAUTHORIZED))
+    }
+
+    if let Some(session_id) = claims.sid {
+        let active = get_session(session_id).await?
+            .is_some_and(|session| session.user_id == user_id && session.revoked_at.is_none());
+        if !active {
+            return Err(AuthError("Session was logged out".to_string(), StatusCode::UNAUTHORIZED))
+        }
+        touch_session(session_id).await?;
+    }
+
+    AuthUser::from_token(user, &claims)
+}
+
+async fn authenticate_api_key(key: &str) -> Result<AuthUser, AuthError> {
+    let (stored, user) = resolve_api_key(key).await?;
+    let key_id = stored.id.ok_or_else(|| AuthError("API key has no id".to_string(), StatusCode::INTERNAL_SERVER_ERROR))?;
+    // A key never outranks its owner, e.g. an admin key stops being one when the owner loses admin.
+    let granted = Scope::for_user(&user);
+    let scopes = Scope::parse_list(&stored.scopes).into_iter().filter(|scope| granted.contains(scope)).collect();
+    AuthUser::from_api_key(user, key_id, scopes)
+}
+
+/// Accepts `Authorization: Bearer <jwt>` and `Authorization: ApiKey <key>`. The state is the scope
+/// the route requires, declared in `main` with `middleware::from_fn_with_state`; `None` lets any credential through.
+pub async fn authenticate(State(required): State<Option<Scope>>, mut req:Request, next: Next ) -> Result<Response<Body>, AuthError>{
     let auth_header = req.headers().get(http::header::AUTHORIZATION);
     let auth_header = match auth_header {
         Some(header) => { header.to_str().map_err(|_| AuthError("Empty header is not allowed".to_string(), StatusCode::FORBIDDEN))},
@@ -42,11 +171,18 @@ pub async fn authenticate(mut req:Request, next: Next ) -> Result<Response<Body>
     }?;
     
     let mut header = auth_header.split_whitespace();
-    let (bearer, token) = (header.next(), header.next());
-    let token_data = decode_jwt(token.unwrap().to_string())?;
-    if !check_if_user_exist(token_data.claims).await?{
-        return  Err(AuthError("User in JWT Token does not exist in Database".to_string(), StatusCode::FORBIDDEN))
+    let (scheme, token) = (header.next(), header.next());
+    let token = token.ok_or_else(|| AuthError("Please add JWT to your Header".to_string(), StatusCode::FORBIDDEN))?;
+    let user = match scheme {
+        Some(scheme) if scheme.eq_ignore_ascii_case("ApiKey") => authenticate_api_key(token).await?,
+        _ => authenticate_jwt(token).await?,
+    };
+    if let Some(scope) = required {
+        user.require_scope(scope)?;
     }
+
+    // Handlers pick the user up through the `AuthUser` extractor instead of decoding the token again.
+    req.extensions_mut().insert(user);
     Ok(next.run(req).await)
     
-}
\ No newline at end of file
+}
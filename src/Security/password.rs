@@ -0,0 +1,83 @@
+use std::env;
+use std::sync::OnceLock;
+use argon2::{Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version};
+use argon2::password_hash::SaltString;
+use dotenv::dotenv;
+use rand::rngs::OsRng;
+use crate::model::usermodel::ConversionError;
+
+/// Outcome of checking a password against the stored value.
+pub struct PasswordCheck {
+    pub valid: bool,
+    /// The stored value is plaintext, bcrypt or Argon2 with outdated parameters and should be replaced.
+    pub needs_rehash: bool,
+}
+
+fn env_u32(name: &str, default: u32) -> u32 {
+    env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Argon2id cost read from `ARGON2_MEMORY_KIB`, `ARGON2_ITERATIONS` and `ARGON2_PARALLELISM`.
+/// Defaults follow the OWASP recommendation of 19 MiB, 2 iterations and 1 lane.
+pub fn argon2_params() -> Params {
+    dotenv().ok();
+    let memory = env_u32("ARGON2_MEMORY_KIB", 19 * 1024);
+    let iterations = env_u32("ARGON2_ITERATIONS", 2);
+    let parallelism = env_u32("ARGON2_PARALLELISM", 1);
+    Params::new(memory, iterations, parallelism, None).unwrap_or_else(|error| {
+        println!("Invalid Argon2 parameters ({}), using defaults", error);
+        Params::default()
+    })
+}
+
+fn argon2() -> Argon2<'static> {
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, argon2_params())
+}
+
+/// Hashes with Argon2id and a random salt, the result is a PHC string that carries its own parameters.
+pub fn hash_password(password: &str) -> Result<String, ConversionError> {
+    let salt = SaltString::generate(&mut OsRng);
+    argon2().hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|error| ConversionError::ConversionError(format!("Password hashing failed: {}", error)))
+}
+
+fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    left.len() == right.len() && left.iter().zip(right).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+fn is_current(hash: &PasswordHash) -> bool {
+    let current = argon2_params();
+    hash.algorithm == Algorithm::Argon2id.ident()
+        && Params::try_from(hash).is_ok_and(|params| {
+            params.m_cost() == current.m_cost() && params.t_cost() == current.t_cost() && params.p_cost() == current.p_cost()
+        })
+}
+
+/// Verifies `password` against a stored Argon2 PHC string, a bcrypt hash or a legacy plaintext value.
+pub fn verify_password(password: &str, stored: &str) -> PasswordCheck {
+    if stored.starts_with("$argon2") {
+        return match PasswordHash::new(stored) {
+            Ok(hash) => PasswordCheck {
+                // The verifier takes algorithm and cost from the PHC string, not from the current settings.
+                valid: Argon2::default().verify_password(password.as_bytes(), &hash).is_ok(),
+                needs_rehash: !is_current(&hash),
+            },
+            Err(_) => PasswordCheck { valid: false, needs_rehash: false },
+        }
+    }
+    if stored.starts_with("$2") {
+        return PasswordCheck { valid: bcrypt::verify(password, stored).unwrap_or(false), needs_rehash: true }
+    }
+    PasswordCheck { valid: constant_time_eq(password.as_bytes(), stored.as_bytes()), needs_rehash: true }
+}
+
+/// Burns the time of a real verification for unknown users, so login timing does not reveal which names exist.
+pub fn verify_dummy_password(password: &str) {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+    let dummy = DUMMY_HASH.get_or_init(|| hash_password("dummy password").unwrap_or_default());
+    verify_password(password, dummy);
+}
@@ -0,0 +1,28 @@
+use std::env;
+use dotenv::dotenv;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+
+/// Draws `length` characters from `[0-9A-Za-z]` with the thread local CSPRNG.
+pub fn random_base62(length: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
+/// Length of share tokens, read from `SHARE_TOKEN_LENGTH` (defaults to 22 characters, about 131 bits).
+/// Values below 16 are raised to 16 so tokens stay unguessable.
+pub fn share_token_length() -> usize {
+    dotenv().ok();
+    env::var("SHARE_TOKEN_LENGTH")
+        .ok()
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(22)
+        .max(16)
+}
+
+pub fn generate_share_token() -> String {
+    random_base62(share_token_length())
+}
@@ -0,0 +1,67 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::sync::{OnceLock, RwLock};
+use std::time::{Duration, Instant};
+use chrono::NaiveDateTime;
+use dotenv::dotenv;
+use crate::model::tokenmodel::RevokedTokenToInsert;
+use crate::model::usermodel::ConversionError;
+use crate::repository::tokenrepository::{insert_revoked_token, load_revoked_token_ids};
+
+/// In-memory copy of the revocation list so `authenticate` does not query it on every request.
+struct RevocationCache {
+    revoked: HashSet<String>,
+    /// Revoked by this process with the time they were added. A reload that read the table before one of
+    /// them was written would drop it, so they are carried over until a reload started after them.
+    recently_revoked: HashMap<String, Instant>,
+    /// When the table was read for the current set.
+    loaded_at: Option<Instant>,
+}
+
+fn cache() -> &'static RwLock<RevocationCache> {
+    static CACHE: OnceLock<RwLock<RevocationCache>> = OnceLock::new();
+    CACHE.get_or_init(|| RwLock::new(RevocationCache { revoked: HashSet::new(), recently_revoked: HashMap::new(), loaded_at: None }))
+}
+
+/// How long the cache is trusted before it is reloaded, read from `REVOCATION_CACHE_TTL` in seconds
+/// (defaults to 30). Revocations made by this process are visible at once, ones made by other
+/// instances sharing the database after at most this long.
+fn cache_ttl() -> Duration {
+    dotenv().ok();
+    let seconds = env::var("REVOCATION_CACHE_TTL")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(30);
+    Duration::from_secs(seconds)
+}
+
+fn is_stale() -> bool {
+    let cache = cache().read().unwrap_or_else(|poisoned| poisoned.into_inner());
+    cache.loaded_at.is_none_or(|loaded_at| loaded_at.elapsed() >= cache_ttl())
+}
+
+pub async fn is_token_revoked(jti: &str) -> Result<bool, ConversionError> {
+    if is_stale() {
+        let load_started = Instant::now();
+        let revoked = load_revoked_token_ids().await?;
+        let mut cache = cache().write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        // A concurrent reload that read the table later already swapped in a fresher set.
+        if cache.loaded_at.is_none_or(|loaded_at| loaded_at < load_started) {
+            let carried_over: Vec<String> = cache.recently_revoked.keys().cloned().collect();
+            cache.revoked = revoked.into_iter().chain(carried_over).collect();
+            // Written before this load read the table, so they are part of `revoked` from now on.
+            cache.recently_revoked.retain(|_, revoked_at| *revoked_at >= load_started);
+            cache.loaded_at = Some(load_started);
+        }
+    }
+    let cache = cache().read().unwrap_or_else(|poisoned| poisoned.into_inner());
+    Ok(cache.revoked.contains(jti))
+}
+
+pub async fn revoke_token(jti: String, user_id: i32, expires_at: NaiveDateTime) -> Result<(), ConversionError> {
+    insert_revoked_token(RevokedTokenToInsert { jti: jti.clone(), user_id, expires_at }).await?;
+    let mut cache = cache().write().unwrap_or_else(|poisoned| poisoned.into_inner());
+    cache.recently_revoked.insert(jti.clone(), Instant::now());
+    cache.revoked.insert(jti);
+    Ok(())
+}
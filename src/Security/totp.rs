@@ -0,0 +1,140 @@
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha1::Sha1;
+
+/// RFC 6238 defaults, the only parameters common authenticator apps support reliably.
+pub const TOTP_DIGITS: u32 = 6;
+pub const TOTP_STEP_SECONDS: u64 = 30;
+/// Steps accepted before and after the current one, covers clocks that drift by up to 30 seconds.
+const TOTP_SKEW_STEPS: u64 = 1;
+/// 160 bits, the length RFC 4226 recommends for HMAC-SHA1.
+const TOTP_SECRET_BYTES: usize = 20;
+
+pub fn generate_totp_secret() -> Vec<u8> {
+    let mut secret = vec![0u8; TOTP_SECRET_BYTES];
+    rand::thread_rng().fill_bytes(&mut secret);
+    secret
+}
+
+/// Secrets are shown and stored in unpadded base32, as expected by `otpauth://` URIs.
+pub fn encode_totp_secret(secret: &[u8]) -> String {
+    BASE32_NOPAD.encode(secret)
+}
+
+pub fn decode_totp_secret(secret: &str) -> Option<Vec<u8>> {
+    BASE32_NOPAD.decode(secret.as_bytes()).ok()
+}
+
+/// RFC 4226 HOTP with HMAC-SHA1 and dynamic truncation.
+pub fn hotp(secret: &[u8], counter: u64, digits: u32) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([digest[offset] & 0x7f, digest[offset + 1], digest[offset + 2], digest[offset + 3]]);
+    format!("{:0width$}", binary % 10u32.pow(digits), width = digits as usize)
+}
+
+/// The code valid at `unix_time`. Pure so it can be checked against the RFC 6238 test vectors.
+pub fn totp_at(secret: &[u8], unix_time: u64, digits: u32) -> String {
+    hotp(secret, unix_time / TOTP_STEP_SECONDS, digits)
+}
+
+/// Returns the time step `code` matched within the allowed skew, so callers can refuse to accept
+/// the same step twice.
+pub fn verify_totp(secret: &[u8], code: &str, unix_time: u64) -> Option<u64> {
+    let code = code.trim();
+    if code.len() != TOTP_DIGITS as usize || !code.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None
+    }
+    let current = unix_time / TOTP_STEP_SECONDS;
+    let mut matched = None;
+    // Every candidate is computed and compared so the timing does not tell which step matched.
+    for step in current.saturating_sub(TOTP_SKEW_STEPS)..=current + TOTP_SKEW_STEPS {
+        let expected = hotp(secret, step, TOTP_DIGITS);
+        let equal = expected.bytes().zip(code.bytes()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0;
+        if equal && matched.is_none() {
+            matched = Some(step);
+        }
+    }
+    matched
+}
+
+/// A step is only accepted once, a code from the last accepted step or an earlier one is a replay.
+/// The store repeats this check atomically when it records the step.
+pub fn is_unused_step(step: u64, last_used_step: Option<i64>) -> bool {
+    last_used_step.is_none_or(|last_used| (step as i64) > last_used)
+}
+
+/// Key URI understood by authenticator apps, usually rendered as a QR code by the client.
+pub fn otpauth_uri(issuer: &str, account: &str, secret: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        urlencoding::encode(issuer),
+        urlencoding::encode(account),
+        secret,
+        urlencoding::encode(issuer),
+        TOTP_DIGITS,
+        TOTP_STEP_SECONDS,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The SHA-1 seed of RFC 6238 Appendix B.
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn matches_rfc6238_sha1_vectors() {
+        let vectors = [
+            (59, "94287082"),
+            (1111111109, "07081804"),
+            (1111111111, "14050471"),
+            (1234567890, "89005924"),
+            (2000000000, "69279037"),
+            (20000000000, "65353130"),
+        ];
+        for (unix_time, expected) in vectors {
+            assert_eq!(totp_at(RFC_SECRET, unix_time, 8), expected, "T = {}", unix_time);
+        }
+    }
+
+    #[test]
+    fn accepts_one_step_of_skew_either_way() {
+        let issued_at = 1111111109;
+        let step = issued_at / TOTP_STEP_SECONDS;
+        let code = totp_at(RFC_SECRET, issued_at, TOTP_DIGITS);
+
+        assert_eq!(verify_totp(RFC_SECRET, &code, issued_at), Some(step));
+        assert_eq!(verify_totp(RFC_SECRET, &code, issued_at - TOTP_STEP_SECONDS), Some(step));
+        assert_eq!(verify_totp(RFC_SECRET, &code, issued_at + TOTP_STEP_SECONDS), Some(step));
+        assert_eq!(verify_totp(RFC_SECRET, &code, issued_at - 2 * TOTP_STEP_SECONDS), None);
+        assert_eq!(verify_totp(RFC_SECRET, &code, issued_at + 2 * TOTP_STEP_SECONDS), None);
+    }
+
+    #[test]
+    fn rejects_malformed_codes() {
+        let issued_at = 1111111109;
+        let code = totp_at(RFC_SECRET, issued_at, TOTP_DIGITS);
+
+        assert_eq!(verify_totp(RFC_SECRET, &format!(" {} ", code), issued_at), Some(issued_at / TOTP_STEP_SECONDS));
+        assert_eq!(verify_totp(RFC_SECRET, &code[1..], issued_at), None);
+        assert_eq!(verify_totp(RFC_SECRET, "12345a", issued_at), None);
+    }
+
+    #[test]
+    fn rejects_replayed_steps() {
+        let issued_at = 1111111109;
+        let code = totp_at(RFC_SECRET, issued_at, TOTP_DIGITS);
+        let step = verify_totp(RFC_SECRET, &code, issued_at).unwrap();
+
+        assert!(is_unused_step(step, None));
+        assert!(is_unused_step(step, Some(step as i64 - 1)));
+        assert!(!is_unused_step(step, Some(step as i64)));
+        assert!(!is_unused_step(step, Some(step as i64 + 1)));
+    }
+}
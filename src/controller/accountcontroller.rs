@@ -0,0 +1,21 @@
+use axum::http::StatusCode;
+use axum::Json;
+use crate::model::mailmodel::{ForgotPasswordRequest, ResetPasswordRequest, VerifyEmailRequest};
+use crate::model::usermodel::ConversionError;
+use crate::service::accountservice::{forgot_password as other_forgot_password, reset_password as other_reset_password, verify_email as other_verify_email};
+
+pub async fn verify_email(Json(request): Json<VerifyEmailRequest>) -> Result<StatusCode, ConversionError> {
+    other_verify_email(&request.token).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Answers the same whether or not the address belongs to an account.
+pub async fn forgot_password(Json(request): Json<ForgotPasswordRequest>) -> StatusCode {
+    other_forgot_password(request.email);
+    StatusCode::ACCEPTED
+}
+
+pub async fn reset_password(Json(request): Json<ResetPasswordRequest>) -> Result<StatusCode, ConversionError> {
+    other_reset_password(&request.token, request.password).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
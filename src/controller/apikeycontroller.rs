@@ -0,0 +1,22 @@
+use axum::extract::Path;
+use axum::http::StatusCode;
+use axum::Json;
+use crate::model::apikeymodel::{ApiKeyResponse, CreateApiKeyRequest, CreatedApiKeyResponse};
+use crate::model::securitymodel::AuthUser;
+use crate::model::usermodel::ConversionError;
+use crate::service::apikeyservice::{create_api_key, list_api_keys, revoke_user_api_key};
+
+pub async fn create_key(user: AuthUser, Json(request): Json<CreateApiKeyRequest>) -> Result<(StatusCode, Json<CreatedApiKeyResponse>), ConversionError> {
+    let created = create_api_key(&user, request).await?;
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+pub async fn list_keys(user: AuthUser) -> Result<Json<Vec<ApiKeyResponse>>, ConversionError> {
+    let keys = list_api_keys(&user).await?;
+    Ok(Json(keys))
+}
+
+pub async fn revoke_key(user: AuthUser, Path(key_id): Path<i32>) -> Result<StatusCode, ConversionError> {
+    revoke_user_api_key(&user, key_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,56 +1,212 @@
 
-use axum::extract::{Multipart, Path};
+use std::net::SocketAddr;
+use axum::extract::{ConnectInfo, Multipart, Path, Query};
+use axum::Json;
 use axum::body::*;
-use axum::response::IntoResponse;
-use axum::http::{header, Response, StatusCode};
+use axum::response::Response;
+use axum::http::{header, HeaderMap, Method, StatusCode};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::{DateTime, NaiveDateTime};
+use crate::model::filemodel::{FileListResponse, FileResponse, GetFileResponse, ListFilesQuery, UpdateFileRequest, UploadQuery};
+use crate::model::securitymodel::AuthUser;
 use crate::model::usermodel::ConversionError;
 use crate::model::usermodel::ConversionError::*;
-use crate::service::fileservice::{get_file_name, store_files};
+use crate::model::storagemodel::ByteRange;
+use crate::service::fileservice::{delete_file as other_delete_file, empty_trash as other_empty_trash, get_file_name, list_files as other_list_files, list_trash as other_list_trash, purge_trashed_file, restore_file as other_restore_file, store_files, update_file as other_update_file};
+use crate::service::sessionservice::session_client;
+use crate::service::shareservice::record_download;
+use crate::storage::storagebackend::storage_backend;
 
 
+enum RangeRequest {
+    Full,
+    Partial(ByteRange),
+    Unsatisfiable,
+}
 
+fn http_date(time: NaiveDateTime) -> String {
+    time.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
 
-pub async fn download(Path(file_link): Path<String>) -> impl IntoResponse{
-    
-    println!("Processing Request");
+fn parse_http_date(value: &str) -> Option<i64> {
+    DateTime::parse_from_rfc2822(value).ok().map(|time| time.timestamp())
+}
 
-    let information = get_file_name(file_link).await;
+/// Maps our hash algorithm names to the tokens of the HTTP digest registry.
+fn digest_algorithm_token(hash_algorithm: &str) -> Option<&'static str> {
+    match hash_algorithm {
+        "sha256" => Some("sha-256"),
+        "blake3" => Some("blake3"),
+        _ => None
+    }
+}
 
+fn etag_matches(header_value: &str, etag: &str) -> bool {
+    header_value.split(',')
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
 
-    match information {
-        Ok(infos) => {
-            let content_types = mime_guess::from_path(&infos.filepath);
-           let file_data = tokio::fs::read(&infos.filepath).await;
-            match file_data {
-                Ok(data) => {
+fn is_not_modified(headers: &HeaderMap, etag: &str, last_modified: Option<NaiveDateTime>) -> bool {
+    // If-None-Match takes precedence over If-Modified-Since (RFC 9110 13.2.2)
+    if let Some(if_none_match) = headers.get(header::IF_NONE_MATCH).and_then(|v| v.to_str().ok()) {
+        return etag_matches(if_none_match, etag)
+    }
+    match (headers.get(header::IF_MODIFIED_SINCE).and_then(|v| v.to_str().ok()).and_then(parse_http_date), last_modified) {
+        (Some(since), Some(modified)) => modified.and_utc().timestamp() <= since,
+        _ => false
+    }
+}
 
-                    let body = Body::from(data);
-                    
-                    Response::builder()
-                        .header(header::CONTENT_TYPE, content_types.first_raw().unwrap())
-                        .body(body)
-                        .unwrap()
+fn parse_range(value: &str, size: u64) -> RangeRequest {
+    let Some(spec) = value.trim().strip_prefix("bytes=") else {
+        return RangeRequest::Full
+    };
+    // Multiple ranges would need a multipart/byteranges body, serving the whole file is allowed instead.
+    if spec.contains(',') {
+        return RangeRequest::Full
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return RangeRequest::Full
+    };
 
-                }
-                Err(_) => {
-                    println!("Error Reading Data");
-                     (StatusCode::INTERNAL_SERVER_ERROR, "File not found").into_response()
-                }
-            }
+    if start.is_empty() {
+        return match end.parse::<u64>() {
+            Ok(0) => RangeRequest::Unsatisfiable,
+            Ok(_) if size == 0 => RangeRequest::Unsatisfiable,
+            Ok(suffix) => RangeRequest::Partial(ByteRange { start: size.saturating_sub(suffix), end: size - 1 }),
+            Err(_) => RangeRequest::Full
+        }
+    }
 
+    let Ok(start) = start.parse::<u64>() else {
+        return RangeRequest::Full
+    };
+    let end = if end.is_empty() {
+        size.saturating_sub(1)
+    } else {
+        match end.parse::<u64>() {
+            Ok(end) if end < start => return RangeRequest::Full,
+            Ok(end) => end.min(size.saturating_sub(1)),
+            Err(_) => return RangeRequest::Full
         }
-        Err(error) => {
-            println!("Error message while try to get File Path: {}", error);
-            (StatusCode::INTERNAL_SERVER_ERROR).into_response()
+    };
+    if start >= size {
+        return RangeRequest::Unsatisfiable
+    }
+    RangeRequest::Partial(ByteRange { start, end })
+}
+
+fn requested_range(headers: &HeaderMap, etag: &str, last_modified: Option<NaiveDateTime>, size: u64) -> RangeRequest {
+    let Some(range) = headers.get(header::RANGE).and_then(|v| v.to_str().ok()) else {
+        return RangeRequest::Full
+    };
+    // A Range guarded by If-Range only applies while the representation is unchanged, otherwise send everything.
+    if let Some(if_range) = headers.get(header::IF_RANGE).and_then(|v| v.to_str().ok()) {
+        let unchanged = if if_range.starts_with('"') || if_range.starts_with("W/") {
+            if_range == etag
+        } else {
+            match (parse_http_date(if_range), last_modified) {
+                (Some(date), Some(modified)) => modified.and_utc().timestamp() == date,
+                _ => false
+            }
+        };
+        if !unchanged {
+            return RangeRequest::Full
         }
     }
+    parse_range(range, size)
+}
+
+/// `attachment` with the original name: a plain ASCII `filename` for old clients and the exact name as
+/// RFC 8187 `filename*`, which takes precedence wherever it is understood.
+fn content_disposition(file_name: &str) -> String {
+    let fallback: String = file_name.chars()
+        .map(|c| if c == ' ' || (c.is_ascii_graphic() && c != '"' && c != '\\') { c } else { '_' })
+        .collect();
+    format!("attachment; filename=\"{}\"; filename*=UTF-8''{}", fallback, urlencoding::encode(file_name))
+}
 
+/// Header carrying the password of a protected share link.
+const SHARE_PASSWORD_HEADER: &str = "X-Share-Password";
+
+pub async fn download(ConnectInfo(peer): ConnectInfo<SocketAddr>, method: Method, Path(file_link): Path<String>, headers: HeaderMap) -> Result<Response, ConversionError>{
+    
+    println!("Processing Request");
 
+    let password = headers.get(SHARE_PASSWORD_HEADER).and_then(|v| v.to_str().ok());
+    let client = session_client(&headers, peer);
+    let infos = get_file_name(file_link, password, client.ip_address.as_deref()).await?;
+    serve_file(method, &headers, infos).await
 }
 
-pub async fn upload_file(file: Multipart) -> Result<String,ConversionError>{
+/// Sends stored content with caching, range and digest headers. Shared by link downloads and version downloads.
+pub async fn serve_file(method: Method, headers: &HeaderMap, infos: GetFileResponse) -> Result<Response, ConversionError> {
+    let storage = storage_backend().await;
+    let meta = storage.head(&infos.filepath).await?;
+    let size = meta.size;
+    let etag = format!("\"{}\"", infos.content_hash);
+    let last_modified = infos.last_modified.or(meta.last_modified);
 
-    let is_stored = store_files(file).await;
+    let mut response = Response::builder()
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::ETAG, &etag)
+        .header(header::CONTENT_DISPOSITION, content_disposition(&infos.filename));
+    if let Some(modified) = last_modified {
+        response = response.header(header::LAST_MODIFIED, http_date(modified));
+    }
+    let token = infos.hash_algorithm.as_deref().and_then(digest_algorithm_token);
+    if let (Some(token), Ok(digest)) = (token, hex::decode(&infos.content_hash)) {
+        let digest = STANDARD.encode(digest);
+        response = response
+            .header("Repr-Digest", format!("{}=:{}:", token, digest))
+            .header("Digest", format!("{}={}", token, digest));
+    }
+
+    if is_not_modified(headers, &etag, last_modified) {
+        return Ok(response.status(StatusCode::NOT_MODIFIED).body(Body::empty()).unwrap())
+    }
+
+    let range = match requested_range(headers, &etag, last_modified, size) {
+        RangeRequest::Full => None,
+        RangeRequest::Partial(range) => Some(range),
+        RangeRequest::Unsatisfiable => {
+            return Ok(response
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", size))
+                .body(Body::empty())
+                .unwrap())
+        }
+    };
+
+    // The name carries the real extension, rows from before only have the MIME subtype to go on.
+    let content_type = mime_guess::from_path(&infos.filename).first_raw()
+        .or_else(|| mime_guess::from_ext(&infos.content_type).first_raw())
+        .unwrap_or("application/octet-stream");
+    let stream = storage.get_stream(&infos.filepath, range).await?;
+    // Every transfer counts, resumed range requests included, so ranges can not be used to dodge the limit.
+    // Counted once the content could be opened, a storage failure does not use up a download.
+    if method != Method::HEAD && let Some(share) = &infos.share {
+        record_download(share).await?;
+    }
+    let response = response.header(header::CONTENT_TYPE, content_type);
+    let response = match range {
+        Some(range) => response
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", range.start, range.end, size))
+            .header(header::CONTENT_LENGTH, range.length()),
+        None => response
+            .status(StatusCode::OK)
+            .header(header::CONTENT_LENGTH, size)
+    };
+
+    Ok(response.body(Body::from_stream(stream)).unwrap())
+}
+
+pub async fn upload_file(user: AuthUser, Query(query): Query<UploadQuery>, file: Multipart) -> Result<String,ConversionError>{
+
+    let is_stored = store_files(file, &user, query.folder_id, query.on_conflict).await;
     match is_stored {
         Ok(links) => {
 
@@ -71,3 +227,134 @@ pub async fn upload_file(file: Multipart) -> Result<String,ConversionError>{
 
 }
 
+
+pub async fn list_files(user: AuthUser, Query(query): Query<ListFilesQuery>) -> Result<Json<FileListResponse>, ConversionError> {
+    let files = other_list_files(&user, query).await?;
+    Ok(Json(files))
+}
+
+pub async fn update_file(user: AuthUser, Path(file_id): Path<i32>, Json(request): Json<UpdateFileRequest>) -> Result<Json<FileResponse>, ConversionError> {
+    let updated = other_update_file(&user, file_id, request).await?;
+    Ok(Json(updated))
+}
+
+pub async fn delete_file(user: AuthUser, Path(file_id): Path<i32>) -> Result<StatusCode, ConversionError> {
+    other_delete_file(&user, file_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_trash(user: AuthUser) -> Result<Json<Vec<FileResponse>>, ConversionError> {
+    let files = other_list_trash(&user).await?;
+    Ok(Json(files))
+}
+
+pub async fn restore_file(user: AuthUser, Path(file_id): Path<i32>) -> Result<Json<FileResponse>, ConversionError> {
+    let restored = other_restore_file(&user, file_id).await?;
+    Ok(Json(restored))
+}
+
+pub async fn purge_file(user: AuthUser, Path(file_id): Path<i32>) -> Result<StatusCode, ConversionError> {
+    purge_trashed_file(&user, file_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn empty_trash(user: AuthUser) -> Result<StatusCode, ConversionError> {
+    other_empty_trash(&user).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    const ETAG: &str = "\"abc123\"";
+    const RANGE: (header::HeaderName, &str) = (header::RANGE, "bytes=0-9");
+
+    fn partial(request: RangeRequest) -> Option<(u64, u64)> {
+        match request {
+            RangeRequest::Partial(range) => Some((range.start, range.end)),
+            _ => None,
+        }
+    }
+
+    fn headers(pairs: &[(header::HeaderName, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(name.clone(), HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    fn modified() -> Option<NaiveDateTime> {
+        DateTime::parse_from_rfc2822("Sun, 18 Oct 2026 12:00:00 GMT").ok().map(|time| time.naive_utc())
+    }
+
+    #[test]
+    fn etags_match_weakly_and_in_lists() {
+        assert!(etag_matches(ETAG, ETAG));
+        assert!(etag_matches("W/\"abc123\"", ETAG));
+        assert!(etag_matches("\"other\", \"abc123\"", ETAG));
+        assert!(etag_matches("*", ETAG));
+        assert!(!etag_matches("\"other\"", ETAG));
+        assert!(!etag_matches("abc123", ETAG));
+    }
+
+    #[test]
+    fn if_none_match_wins_over_if_modified_since() {
+        let since = "Sun, 18 Oct 2026 13:00:00 GMT";
+        assert!(is_not_modified(&headers(&[(header::IF_NONE_MATCH, ETAG)]), ETAG, modified()));
+        assert!(is_not_modified(&headers(&[(header::IF_MODIFIED_SINCE, since)]), ETAG, modified()));
+        assert!(!is_not_modified(&headers(&[(header::IF_NONE_MATCH, "\"other\""), (header::IF_MODIFIED_SINCE, since)]), ETAG, modified()));
+        assert!(!is_not_modified(&headers(&[(header::IF_MODIFIED_SINCE, "Sun, 18 Oct 2026 11:00:00 GMT")]), ETAG, modified()));
+        assert!(!is_not_modified(&headers(&[(header::IF_MODIFIED_SINCE, "yesterday")]), ETAG, modified()));
+        assert!(!is_not_modified(&HeaderMap::new(), ETAG, modified()));
+    }
+
+    #[test]
+    fn parses_closed_open_and_suffix_ranges() {
+        assert_eq!(partial(parse_range("bytes=0-99", 1000)), Some((0, 99)));
+        assert_eq!(partial(parse_range("bytes=900-2000", 1000)), Some((900, 999)));
+        assert_eq!(partial(parse_range("bytes=500-", 1000)), Some((500, 999)));
+        assert_eq!(partial(parse_range("bytes=-100", 1000)), Some((900, 999)));
+        assert_eq!(partial(parse_range("bytes=-5000", 1000)), Some((0, 999)));
+    }
+
+    #[test]
+    fn ranges_past_the_end_are_unsatisfiable() {
+        assert!(matches!(parse_range("bytes=1000-", 1000), RangeRequest::Unsatisfiable));
+        assert!(matches!(parse_range("bytes=1500-1600", 1000), RangeRequest::Unsatisfiable));
+        assert!(matches!(parse_range("bytes=-0", 1000), RangeRequest::Unsatisfiable));
+        assert!(matches!(parse_range("bytes=-10", 0), RangeRequest::Unsatisfiable));
+        assert!(matches!(parse_range("bytes=0-", 0), RangeRequest::Unsatisfiable));
+    }
+
+    #[test]
+    fn invalid_and_multiple_ranges_fall_back_to_the_full_file() {
+        assert!(matches!(parse_range("bytes=0-10,20-30", 1000), RangeRequest::Full));
+        assert!(matches!(parse_range("bytes=20-10", 1000), RangeRequest::Full));
+        assert!(matches!(parse_range("bytes=abc-", 1000), RangeRequest::Full));
+        assert!(matches!(parse_range("bytes=10", 1000), RangeRequest::Full));
+        assert!(matches!(parse_range("items=0-10", 1000), RangeRequest::Full));
+    }
+
+    #[test]
+    fn if_range_only_applies_the_range_while_unchanged() {
+        assert_eq!(partial(requested_range(&headers(&[RANGE]), ETAG, modified(), 100)), Some((0, 9)));
+        assert_eq!(partial(requested_range(&headers(&[RANGE, (header::IF_RANGE, ETAG)]), ETAG, modified(), 100)), Some((0, 9)));
+        assert!(matches!(requested_range(&headers(&[RANGE, (header::IF_RANGE, "\"stale\"")]), ETAG, modified(), 100), RangeRequest::Full));
+        // If-Range compares strongly, a weak validator never matches.
+        assert!(matches!(requested_range(&headers(&[RANGE, (header::IF_RANGE, "W/\"abc123\"")]), ETAG, modified(), 100), RangeRequest::Full));
+        assert_eq!(partial(requested_range(&headers(&[RANGE, (header::IF_RANGE, "Sun, 18 Oct 2026 12:00:00 GMT")]), ETAG, modified(), 100)), Some((0, 9)));
+        assert!(matches!(requested_range(&headers(&[RANGE, (header::IF_RANGE, "Sun, 18 Oct 2026 11:00:00 GMT")]), ETAG, modified(), 100), RangeRequest::Full));
+        assert!(matches!(requested_range(&HeaderMap::new(), ETAG, modified(), 100), RangeRequest::Full));
+    }
+
+    #[test]
+    fn content_disposition_keeps_non_ascii_names() {
+        assert_eq!(content_disposition("report.pdf"), "attachment; filename=\"report.pdf\"; filename*=UTF-8''report.pdf");
+        assert_eq!(content_disposition("my file.txt"), "attachment; filename=\"my file.txt\"; filename*=UTF-8''my%20file.txt");
+        assert_eq!(content_disposition("Grüße.txt"), "attachment; filename=\"Gr__e.txt\"; filename*=UTF-8''Gr%C3%BC%C3%9Fe.txt");
+        assert_eq!(content_disposition("a\"b\\c.txt"), "attachment; filename=\"a_b_c.txt\"; filename*=UTF-8''a%22b%5Cc.txt");
+    }
+}
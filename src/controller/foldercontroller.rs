@@ -0,0 +1,32 @@
+use axum::extract::{Path, Query};
+use axum::http::StatusCode;
+use axum::Json;
+use crate::model::foldermodel::{CreateFolderRequest, DeleteFolderQuery, FolderListingResponse, FolderResponse, UpdateFolderRequest};
+use crate::model::securitymodel::AuthUser;
+use crate::model::usermodel::ConversionError;
+use crate::service::folderservice::{create_folder as other_create_folder, delete_folder as other_delete_folder, list_folder, update_folder as other_update_folder};
+
+pub async fn create_folder(user: AuthUser, Json(request): Json<CreateFolderRequest>) -> Result<(StatusCode, Json<FolderResponse>), ConversionError> {
+    let created = other_create_folder(&user, request).await?;
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+pub async fn list_root(user: AuthUser) -> Result<Json<FolderListingResponse>, ConversionError> {
+    let listing = list_folder(&user, None).await?;
+    Ok(Json(listing))
+}
+
+pub async fn get_folder(user: AuthUser, Path(folder_id): Path<i32>) -> Result<Json<FolderListingResponse>, ConversionError> {
+    let listing = list_folder(&user, Some(folder_id)).await?;
+    Ok(Json(listing))
+}
+
+pub async fn update_folder(user: AuthUser, Path(folder_id): Path<i32>, Json(request): Json<UpdateFolderRequest>) -> Result<Json<FolderResponse>, ConversionError> {
+    let updated = other_update_folder(&user, folder_id, request).await?;
+    Ok(Json(updated))
+}
+
+pub async fn delete_folder(user: AuthUser, Path(folder_id): Path<i32>, Query(query): Query<DeleteFolderQuery>) -> Result<StatusCode, ConversionError> {
+    other_delete_folder(&user, folder_id, query.recursive).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,31 @@
+use std::net::SocketAddr;
+use axum::extract::ConnectInfo;
+use axum::http::{HeaderMap, StatusCode};
+use axum::Json;
+use crate::model::mfamodel::{MfaCodeRequest, RecoveryCodesResponse, TotpEnrollmentResponse};
+use crate::model::securitymodel::AuthUser;
+use crate::model::usermodel::ConversionError;
+use crate::service::mfaservice::{confirm_totp_enrollment, disable_mfa, regenerate_recovery_codes, start_totp_enrollment};
+use crate::service::sessionservice::session_client;
+
+pub async fn enroll_totp(user: AuthUser) -> Result<Json<TotpEnrollmentResponse>, ConversionError> {
+    let enrollment = start_totp_enrollment(&user).await?;
+    Ok(Json(enrollment))
+}
+
+pub async fn confirm_totp(user: AuthUser, Json(request): Json<MfaCodeRequest>) -> Result<Json<RecoveryCodesResponse>, ConversionError> {
+    let codes = confirm_totp_enrollment(&user, &request.code).await?;
+    Ok(Json(codes))
+}
+
+pub async fn disable_totp(ConnectInfo(peer): ConnectInfo<SocketAddr>, headers: HeaderMap, user: AuthUser, Json(request): Json<MfaCodeRequest>) -> Result<StatusCode, ConversionError> {
+    let client = session_client(&headers, peer);
+    disable_mfa(&user, &request.code, client.ip_address.as_deref()).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn regenerate_codes(ConnectInfo(peer): ConnectInfo<SocketAddr>, headers: HeaderMap, user: AuthUser, Json(request): Json<MfaCodeRequest>) -> Result<Json<RecoveryCodesResponse>, ConversionError> {
+    let client = session_client(&headers, peer);
+    let codes = regenerate_recovery_codes(&user, &request.code, client.ip_address.as_deref()).await?;
+    Ok(Json(codes))
+}
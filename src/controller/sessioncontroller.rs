@@ -0,0 +1,19 @@
+use axum::extract::Path;
+use axum::http::StatusCode;
+use axum::Json;
+use crate::model::securitymodel::AuthUser;
+use crate::model::sessionmodel::SessionResponse;
+use crate::model::usermodel::ConversionError;
+use crate::service::sessionservice::{list_sessions as other_list_sessions, revoke_user_session};
+
+pub async fn list_sessions(user: AuthUser) -> Result<Json<Vec<SessionResponse>>, ConversionError> {
+    user.require_login()?;
+    let sessions = other_list_sessions(&user).await?;
+    Ok(Json(sessions))
+}
+
+pub async fn delete_session(user: AuthUser, Path(session_id): Path<i32>) -> Result<StatusCode, ConversionError> {
+    user.require_login()?;
+    revoke_user_session(&user, session_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
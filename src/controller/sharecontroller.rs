@@ -0,0 +1,22 @@
+use axum::extract::Path;
+use axum::http::StatusCode;
+use axum::Json;
+use crate::model::securitymodel::AuthUser;
+use crate::model::sharemodel::{CreateShareLinkRequest, ShareLinkResponse};
+use crate::model::usermodel::ConversionError;
+use crate::service::shareservice::{create_share_link_for_file, list_share_links, revoke_link};
+
+pub async fn create_link(user: AuthUser, Path(file_id): Path<i32>, Json(request): Json<CreateShareLinkRequest>) -> Result<(StatusCode, Json<ShareLinkResponse>), ConversionError> {
+    let share = create_share_link_for_file(&user, file_id, request).await?;
+    Ok((StatusCode::CREATED, Json(share)))
+}
+
+pub async fn list_links(user: AuthUser, Path(file_id): Path<i32>) -> Result<Json<Vec<ShareLinkResponse>>, ConversionError> {
+    let links = list_share_links(&user, file_id).await?;
+    Ok(Json(links))
+}
+
+pub async fn revoke(user: AuthUser, Path(link_id): Path<i32>) -> Result<StatusCode, ConversionError> {
+    revoke_link(&user, link_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,22 @@
+use axum::http::StatusCode;
+use axum::Json;
+use crate::model::securitymodel::AuthUser;
+use crate::model::tokenmodel::{LogoutRequest, RefreshRequest};
+use crate::model::usermodel::{ConversionError, LoginResponse};
+use crate::service::tokenservice::{logout as other_logout, refresh_tokens, revoke_all_tokens};
+
+pub async fn refresh(Json(request): Json<RefreshRequest>) -> Result<LoginResponse, ConversionError> {
+    refresh_tokens(request.refresh_token).await
+}
+
+pub async fn logout(user: AuthUser, request: Option<Json<LogoutRequest>>) -> Result<StatusCode, ConversionError> {
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    other_logout(&user, request).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn revoke_all(user: AuthUser) -> Result<StatusCode, ConversionError> {
+    user.require_login()?;
+    revoke_all_tokens(&user).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,100 @@
+use axum::body::Body;
+use axum::extract::Path;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::Response;
+use crate::model::securitymodel::AuthUser;
+use crate::model::tusmodel::{TusError, TUS_CHECKSUM_ALGORITHMS, TUS_EXTENSIONS, TUS_VERSION};
+use crate::service::fileservice::max_upload_size;
+use crate::service::tusservice::{append_chunk, create_upload as other_create_upload, parse_upload_metadata, terminate_upload as other_terminate_upload, upload_status as other_upload_status};
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn check_tus_resumable(headers: &HeaderMap) -> Result<(), TusError> {
+    match header_str(headers, "Tus-Resumable") {
+        Some(TUS_VERSION) => Ok(()),
+        _ => Err(TusError::new("Unsupported or missing Tus-Resumable version", StatusCode::PRECONDITION_FAILED)),
+    }
+}
+
+fn parse_i64_header(headers: &HeaderMap, name: &str) -> Result<i64, TusError> {
+    header_str(headers, name)
+        .and_then(|value| value.parse::<i64>().ok())
+        .filter(|value| *value >= 0)
+        .ok_or_else(|| TusError::new(&format!("Missing or invalid {} header", name), StatusCode::BAD_REQUEST))
+}
+
+pub async fn tus_options() -> Response {
+    Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .header("Tus-Resumable", TUS_VERSION)
+        .header("Tus-Version", TUS_VERSION)
+        .header("Tus-Extension", TUS_EXTENSIONS)
+        .header("Tus-Checksum-Algorithm", TUS_CHECKSUM_ALGORITHMS)
+        .header("Tus-Max-Size", max_upload_size())
+        .body(Body::empty())
+        .unwrap()
+}
+
+pub async fn create_upload(user: AuthUser, headers: HeaderMap) -> Result<Response, TusError> {
+    check_tus_resumable(&headers)?;
+    if headers.contains_key("Upload-Defer-Length") {
+        return Err(TusError::new("Upload-Defer-Length is not supported", StatusCode::BAD_REQUEST))
+    }
+    let upload_length = parse_i64_header(&headers, "Upload-Length")?;
+    let metadata = parse_upload_metadata(header_str(&headers, "Upload-Metadata").unwrap_or(""))?;
+
+    let upload = other_create_upload(&user, upload_length, metadata).await?;
+
+    Ok(Response::builder()
+        .status(StatusCode::CREATED)
+        .header(header::LOCATION, format!("/api/tus/{}", upload.upload_id))
+        .header("Tus-Resumable", TUS_VERSION)
+        .body(Body::empty())
+        .unwrap())
+}
+
+pub async fn upload_status(user: AuthUser, Path(upload_id): Path<String>, headers: HeaderMap) -> Result<Response, TusError> {
+    check_tus_resumable(&headers)?;
+    let upload = other_upload_status(&user, upload_id).await?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("Upload-Offset", upload.upload_offset)
+        .header("Upload-Length", upload.upload_length)
+        .header(header::CACHE_CONTROL, "no-store")
+        .header("Tus-Resumable", TUS_VERSION)
+        .body(Body::empty())
+        .unwrap())
+}
+
+pub async fn upload_chunk(user: AuthUser, Path(upload_id): Path<String>, headers: HeaderMap, body: Body) -> Result<Response, TusError> {
+    check_tus_resumable(&headers)?;
+    if header_str(&headers, header::CONTENT_TYPE.as_str()) != Some("application/offset+octet-stream") {
+        return Err(TusError::new("Content-Type must be application/offset+octet-stream", StatusCode::UNSUPPORTED_MEDIA_TYPE))
+    }
+    let offset = parse_i64_header(&headers, "Upload-Offset")?;
+
+    let result = append_chunk(&user, upload_id, offset, header_str(&headers, "Upload-Checksum"), body).await?;
+
+    let mut response = Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .header("Upload-Offset", result.upload_offset)
+        .header("Tus-Resumable", TUS_VERSION);
+    if let Some(file_link) = result.file_link {
+        response = response.header("File-Link", file_link);
+    }
+    Ok(response.body(Body::empty()).unwrap())
+}
+
+pub async fn terminate_upload(user: AuthUser, Path(upload_id): Path<String>, headers: HeaderMap) -> Result<Response, TusError> {
+    check_tus_resumable(&headers)?;
+    other_terminate_upload(&user, upload_id).await?;
+
+    Ok(Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .header("Tus-Resumable", TUS_VERSION)
+        .body(Body::empty())
+        .unwrap())
+}
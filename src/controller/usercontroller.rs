@@ -1,39 +1,63 @@
-use axum::http::{StatusCode};
+use std::net::SocketAddr;
+use axum::extract::ConnectInfo;
+use axum::http::{HeaderMap, StatusCode};
 use axum::{ Json};
-use axum::response::IntoResponse;
-use crate::model::securitymodel::AuthError;
-use crate::model::usermodel::{CreateUserRequest, LoginRequest, LoginResponse};
-use crate::Security::jwt::encode_jwt;
-use crate::service::userservice::{check_user_login, create_user};
+use crate::model::mfamodel::MfaLoginRequest;
+use crate::model::throttlemodel::LoginThrottleKeys;
+use crate::model::usermodel::{ConversionError, CreateUserRequest, LoginOutcome, LoginRequest, LoginResponse};
+use crate::Security::jwt::{encode_mfa_challenge, mfa_challenge_ttl};
+use crate::service::accountservice::require_email_verification;
+use crate::service::mfaservice::{complete_mfa_login, user_requires_mfa};
+use crate::service::sessionservice::session_client;
+use crate::service::throttleservice::{record_login_failure, record_login_success, refund_login_attempt, reserve_login_attempt};
+use crate::service::tokenservice::issue_tokens;
+use crate::service::userservice::{check_user_login, create_user, find_login_user};
 
 // #[axum::debug_handler]
-pub async fn signup(Json(user):Json<CreateUserRequest> ) -> impl IntoResponse{
+pub async fn signup(Json(user):Json<CreateUserRequest> ) -> Result<StatusCode, ConversionError>{
     
-    let result =   create_user(user).await;
+    let result =   create_user(user).await?;
     
-    if result == true{
-        StatusCode::OK
+    if result {
+        Ok(StatusCode::OK)
     }
     else { 
-        StatusCode::CONFLICT
+        Ok(StatusCode::CONFLICT)
     }
 }
 
-pub async fn login(Json(user):Json<LoginRequest>) -> Result<LoginResponse, AuthError>{
-    
-    if check_user_login(user.clone()).await?{
-        let token = encode_jwt(&user.name, user.email.as_str())?;
-        
-        let response = LoginResponse{
-            status_code: StatusCode::OK,
-            jwt_token: token
-        };
+pub async fn login(ConnectInfo(peer): ConnectInfo<SocketAddr>, headers: HeaderMap, Json(request):Json<LoginRequest>) -> Result<LoginOutcome, ConversionError>{
+    let client = session_client(&headers, peer);
+    let stored_user = find_login_user(request.identifier.clone()).await?;
+    let keys = LoginThrottleKeys::new(&request.identifier, stored_user.as_ref(), client.ip_address.as_deref());
+    let attempt = reserve_login_attempt(&keys).await?;
+
+    // The token is issued from the stored row, nothing the client sent besides the identifier is trusted.
+    if let Some(user) = check_user_login(stored_user, request.password).await?{
+        let user_id = user.id.ok_or_else(|| ConversionError::ConversionError("User has no id".to_string()))?;
+        // Only told after the password matched, so this does not reveal which addresses have accounts.
+        if require_email_verification() && user.email_verified_at.is_none() {
+            refund_login_attempt(attempt).await?;
+            return Err(ConversionError::Forbidden("Email address is not verified".to_string()))
+        }
+        if user_requires_mfa(user_id).await? {
+            // Failures are only cleared once the second factor is passed as well.
+            refund_login_attempt(attempt).await?;
+            return Ok(LoginOutcome::MfaRequired { mfa_token: encode_mfa_challenge(user_id)?, expires_in: mfa_challenge_ttl() })
+        }
+        record_login_success(attempt).await?;
+        let response = issue_tokens(&user, client).await?;
         
-        Ok(response)
+        Ok(LoginOutcome::Tokens(response))
     }
     else { 
-        Err(AuthError::AuthError("auth failed".to_string(), StatusCode::FORBIDDEN))
+        record_login_failure(attempt).await?;
+        Err(ConversionError::Forbidden("auth failed".to_string()))
     }
     
 }
 
+pub async fn login_mfa(ConnectInfo(peer): ConnectInfo<SocketAddr>, headers: HeaderMap, Json(request): Json<MfaLoginRequest>) -> Result<LoginResponse, ConversionError> {
+    complete_mfa_login(request, session_client(&headers, peer)).await
+}
+
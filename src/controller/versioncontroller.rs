@@ -0,0 +1,25 @@
+use axum::extract::Path;
+use axum::http::{HeaderMap, Method};
+use axum::response::Response;
+use axum::Json;
+use crate::controller::filecontroller::serve_file;
+use crate::model::filemodel::FileResponse;
+use crate::model::securitymodel::AuthUser;
+use crate::model::usermodel::ConversionError;
+use crate::model::versionmodel::FileVersionResponse;
+use crate::service::versionservice::{list_versions as other_list_versions, load_version_content, revert_file};
+
+pub async fn list_versions(user: AuthUser, Path(file_id): Path<i32>) -> Result<Json<Vec<FileVersionResponse>>, ConversionError> {
+    let versions = other_list_versions(&user, file_id).await?;
+    Ok(Json(versions))
+}
+
+pub async fn download_version(user: AuthUser, method: Method, Path((file_id, version)): Path<(i32, i32)>, headers: HeaderMap) -> Result<Response, ConversionError> {
+    let infos = load_version_content(&user, file_id, version).await?;
+    serve_file(method, &headers, infos).await
+}
+
+pub async fn revert_version(user: AuthUser, Path((file_id, version)): Path<(i32, i32)>) -> Result<Json<FileResponse>, ConversionError> {
+    let reverted = revert_file(&user, file_id, version).await?;
+    Ok(Json(reverted))
+}
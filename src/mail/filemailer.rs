@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+use async_trait::async_trait;
+use chrono::Utc;
+use lettre::Message;
+use crate::mail::mailer::Mailer;
+use crate::mail::smtpmailer::build_message;
+use crate::model::mailmodel::{MailError, MailMessage};
+
+/// Writes every mail as an `.eml` file into a directory, where tests and local setups can pick it up.
+pub struct FileMailer {
+    directory: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        FileMailer { directory: directory.into() }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, message: MailMessage) -> Result<(), MailError> {
+        let message: Message = build_message(&message)?;
+        tokio::fs::create_dir_all(&self.directory).await?;
+        let file_name = format!("{}-{}.eml", Utc::now().format("%Y%m%dT%H%M%S%.6f"), uuid::Uuid::new_v4().simple());
+        tokio::fs::write(self.directory.join(file_name), message.formatted()).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn writes_one_eml_file_per_mail() {
+        let directory = std::env::temp_dir().join(format!("fileshare-mail-{}", uuid::Uuid::new_v4().simple()));
+        let mailer = FileMailer::new(&directory);
+        mailer.send(MailMessage { to: "bob@example.org".to_string(), subject: "Hello".to_string(), body: "Reset link".to_string() }).await.unwrap();
+
+        let mut entries = std::fs::read_dir(&directory).unwrap().map(|entry| entry.unwrap().path()).collect::<Vec<_>>();
+        assert_eq!(entries.len(), 1);
+        let path = entries.pop().unwrap();
+        assert_eq!(path.extension().and_then(|extension| extension.to_str()), Some("eml"));
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("To: bob@example.org"));
+        assert!(contents.contains("Reset link"));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_recipients_without_writing() {
+        let directory = std::env::temp_dir().join(format!("fileshare-mail-{}", uuid::Uuid::new_v4().simple()));
+        let mailer = FileMailer::new(&directory);
+        let result = mailer.send(MailMessage { to: "nobody".to_string(), subject: "Hello".to_string(), body: String::new() }).await;
+        assert!(matches!(result, Err(MailError::InvalidAddress(_))));
+        assert!(!directory.exists());
+    }
+}
@@ -0,0 +1,15 @@
+use async_trait::async_trait;
+use crate::mail::mailer::{mail_from, Mailer};
+use crate::model::mailmodel::{MailError, MailMessage};
+
+/// Prints mails to stdout instead of sending them. Meant for development only since the log then
+/// contains the verification and reset links.
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, message: MailMessage) -> Result<(), MailError> {
+        println!("Mail from {} to {}\nSubject: {}\n\n{}", mail_from(), message.to, message.subject, message.body);
+        Ok(())
+    }
+}
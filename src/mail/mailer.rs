@@ -0,0 +1,89 @@
+use std::env;
+use std::sync::Arc;
+use async_trait::async_trait;
+use dotenv::dotenv;
+use tokio::sync::OnceCell;
+use crate::mail::filemailer::FileMailer;
+use crate::mail::logmailer::LogMailer;
+use crate::mail::smtpmailer::SmtpMailer;
+use crate::model::mailmodel::{MailError, MailMessage};
+
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, message: MailMessage) -> Result<(), MailError>;
+}
+
+static MAILER: OnceCell<Arc<dyn Mailer>> = OnceCell::const_new();
+
+enum MailerKind {
+    Smtp,
+    File,
+    Log,
+}
+
+/// `smtp` unless `MAILER` says otherwise. `file` and `log` keep the verification and reset links readable
+/// on the server, so they are only used when asked for by name.
+fn mailer_kind(value: Option<&str>) -> Result<MailerKind, MailError> {
+    match value.map(str::to_lowercase).as_deref() {
+        None | Some("smtp") => Ok(MailerKind::Smtp),
+        Some("file") => Ok(MailerKind::File),
+        Some("log") => Ok(MailerKind::Log),
+        Some(other) => Err(MailError::Backend(format!("Unknown MAILER {}, expected smtp, file or log", other))),
+    }
+}
+
+async fn configured_mailer() -> Result<Arc<dyn Mailer>, MailError> {
+    dotenv().ok();
+    let mailer: Arc<dyn Mailer> = match mailer_kind(env::var("MAILER").ok().as_deref())? {
+        MailerKind::Smtp => {
+            let mailer = SmtpMailer::from_env()?;
+            mailer.verify().await?;
+            Arc::new(mailer)
+        }
+        MailerKind::File => {
+            let directory = env::var("MAIL_DIRECTORY").unwrap_or_else(|_| "mail".to_string());
+            println!("Mails are written to {} instead of being sent", directory);
+            Arc::new(FileMailer::new(directory))
+        }
+        MailerKind::Log => {
+            println!("Mails are printed to this log instead of being sent, do not use MAILER=log in production");
+            Arc::new(LogMailer)
+        }
+    };
+    Ok(mailer)
+}
+
+/// Returns the process wide mailer, selected once via `MAILER` (`smtp`, `file` or `log`, defaults to `smtp`).
+/// An SMTP relay that can not be reached is an error, mails are never quietly diverted to the log.
+pub async fn mailer() -> Result<Arc<dyn Mailer>, MailError> {
+    MAILER.get_or_try_init(configured_mailer).await.cloned()
+}
+
+/// Sender address of every mail, read from `MAIL_FROM`.
+pub fn mail_from() -> String {
+    dotenv().ok();
+    env::var("MAIL_FROM").unwrap_or_else(|_| "Fileshare <no-reply@localhost>".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_to_smtp() {
+        assert!(matches!(mailer_kind(None), Ok(MailerKind::Smtp)));
+        assert!(matches!(mailer_kind(Some("SMTP")), Ok(MailerKind::Smtp)));
+    }
+
+    #[test]
+    fn file_and_log_are_chosen_by_name() {
+        assert!(matches!(mailer_kind(Some("file")), Ok(MailerKind::File)));
+        assert!(matches!(mailer_kind(Some("Log")), Ok(MailerKind::Log)));
+    }
+
+    #[test]
+    fn rejects_unknown_mailers() {
+        assert!(mailer_kind(Some("")).is_err());
+        assert!(mailer_kind(Some("sendmail")).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+use std::env;
+use async_trait::async_trait;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use crate::mail::mailer::{mail_from, Mailer};
+use crate::model::mailmodel::{MailError, MailMessage};
+
+pub fn build_message(message: &MailMessage) -> Result<Message, MailError> {
+    let from = mail_from().parse().map_err(|_| MailError::InvalidAddress(mail_from()))?;
+    let to = message.to.parse().map_err(|_| MailError::InvalidAddress(message.to.clone()))?;
+    Message::builder()
+        .from(from)
+        .to(to)
+        .subject(message.subject.clone())
+        .header(ContentType::TEXT_PLAIN)
+        .body(message.body.clone())
+        .map_err(|error| MailError::Backend(error.to_string()))
+}
+
+/// Sends through an SMTP relay with STARTTLS, or implicit TLS on port 465.
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailer {
+    /// Configured by `SMTP_HOST`, `SMTP_PORT` (defaults to 587), `SMTP_USERNAME` and `SMTP_PASSWORD`.
+    pub fn from_env() -> Result<Self, MailError> {
+        let host = env::var("SMTP_HOST").map_err(|_| MailError::Backend("SMTP_HOST is not set".to_string()))?;
+        let port = match env::var("SMTP_PORT") {
+            Ok(port) => port.parse().map_err(|_| MailError::Backend(format!("Invalid SMTP_PORT {}", port)))?,
+            Err(_) => 587,
+        };
+        let credentials = match (env::var("SMTP_USERNAME"), env::var("SMTP_PASSWORD")) {
+            (Ok(username), Ok(password)) => Some(Credentials::new(username, password)),
+            _ => None,
+        };
+        SmtpMailer::new(&host, port, credentials)
+    }
+
+    pub fn new(host: &str, port: u16, credentials: Option<Credentials>) -> Result<Self, MailError> {
+        let builder = if port == 465 {
+            AsyncSmtpTransport::<Tokio1Executor>::relay(host)
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+        };
+        let mut builder = builder.map_err(|error| MailError::Backend(error.to_string()))?.port(port);
+        if let Some(credentials) = credentials {
+            builder = builder.credentials(credentials);
+        }
+        Ok(SmtpMailer { transport: builder.build() })
+    }
+
+    /// Connects and logs in once, so a wrong host, port or password shows up before the first mail is due.
+    pub async fn verify(&self) -> Result<(), MailError> {
+        match self.transport.test_connection().await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(MailError::Backend("SMTP relay refused the connection".to_string())),
+            Err(error) => Err(MailError::Backend(error.to_string())),
+        }
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, message: MailMessage) -> Result<(), MailError> {
+        self.transport.send(build_message(&message)?).await
+            .map_err(|error| MailError::Backend(error.to_string()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message_to(to: &str) -> MailMessage {
+        MailMessage { to: to.to_string(), subject: "Subject".to_string(), body: "Body".to_string() }
+    }
+
+    #[test]
+    fn builds_plain_text_messages() {
+        let formatted = String::from_utf8(build_message(&message_to("alice@example.org")).unwrap().formatted()).unwrap();
+        assert!(formatted.contains("To: alice@example.org"));
+        assert!(formatted.contains("Subject: Subject"));
+        assert!(formatted.contains("Content-Type: text/plain"));
+    }
+
+    #[test]
+    fn rejects_invalid_recipients() {
+        assert!(matches!(build_message(&message_to("not an address")), Err(MailError::InvalidAddress(_))));
+    }
+
+    #[tokio::test]
+    async fn verify_fails_without_a_relay() {
+        // Nothing listens on port 1, the check has to fail instead of accepting the mailer.
+        let mailer = SmtpMailer::new("127.0.0.1", 1, None).unwrap();
+        assert!(mailer.verify().await.is_err());
+    }
+}
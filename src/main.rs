@@ -1,9 +1,23 @@
 use axum::{middleware, routing::{get, }, Router};
-use axum::routing::post;
-use tower_http::services::ServeDir;
-use crate::controller::filecontroller::{download, upload_file};
-use crate::controller::usercontroller::{login, signup};
+use std::net::SocketAddr;
+use axum::extract::DefaultBodyLimit;
+use axum::routing::{delete, head, patch, post};
+use crate::controller::accountcontroller::{forgot_password, reset_password, verify_email};
+use crate::controller::apikeycontroller::{create_key, list_keys, revoke_key};
+use crate::controller::foldercontroller::{create_folder, delete_folder, get_folder, list_root, update_folder};
+use crate::controller::filecontroller::{delete_file, download, empty_trash, list_files, list_trash, purge_file, restore_file, update_file, upload_file};
+use crate::controller::sessioncontroller::{delete_session, list_sessions};
+use crate::controller::sharecontroller::{create_link, list_links, revoke};
+use crate::controller::tokencontroller::{logout, refresh, revoke_all};
+use crate::controller::tuscontroller::{create_upload, terminate_upload, tus_options, upload_chunk, upload_status};
+use crate::controller::mfacontroller::{confirm_totp, disable_totp, enroll_totp, regenerate_codes};
+use crate::controller::usercontroller::{login, login_mfa, signup};
+use crate::controller::versioncontroller::{download_version, list_versions, revert_version};
+use crate::model::securitymodel::Scope::{FilesRead, FilesWrite, SharesManage};
+use crate::mail::mailer::mailer;
 use crate::Security::jwt::authenticate;
+use crate::service::tusservice::spawn_tus_upload_pruner;
+use crate::service::versionservice::spawn_version_pruner;
 
 #[tokio::main]
 async fn main() {
@@ -11,38 +25,137 @@ async fn main() {
         .route("/", get(hello_world) )
         .route("/api/login", post(login))
         .route("/api/signup", post(signup))
-        .route("/api/upload", post(upload_file).layer(middleware::from_fn(authenticate)))
+        .route("/api/login/mfa", post(login_mfa))
+        .route("/api/token/refresh", post(refresh))
+        .route("/api/verify-email", post(verify_email))
+        .route("/api/password/forgot", post(forgot_password))
+        .route("/api/password/reset", post(reset_password))
+        .route("/api/logout", post(logout).layer(middleware::from_fn_with_state(None, authenticate)))
+        .route("/api/sessions", get(list_sessions).layer(middleware::from_fn_with_state(None, authenticate)))
+        .route("/api/sessions/{session_id}", delete(delete_session).layer(middleware::from_fn_with_state(None, authenticate)))
+        .route("/api/sessions/revoke-all", post(revoke_all).layer(middleware::from_fn_with_state(None, authenticate)))
+        .route("/api/keys", get(list_keys).post(create_key).layer(middleware::from_fn_with_state(None, authenticate)))
+        .route("/api/keys/{key_id}", delete(revoke_key).layer(middleware::from_fn_with_state(None, authenticate)))
+        .route("/api/mfa/totp", post(enroll_totp).delete(disable_totp).layer(middleware::from_fn_with_state(None, authenticate)))
+        .route("/api/mfa/totp/confirm", post(confirm_totp).layer(middleware::from_fn_with_state(None, authenticate)))
+        .route("/api/mfa/recovery-codes", post(regenerate_codes).layer(middleware::from_fn_with_state(None, authenticate)))
+        .route("/api/upload", post(upload_file).layer(DefaultBodyLimit::disable()).layer(middleware::from_fn_with_state(Some(FilesWrite), authenticate)))
         .route("/api/download/{file_link}", get(download))
-        .nest_service("/files", ServeDir::new("content"));
+        .route("/api/files", get(list_files).layer(middleware::from_fn_with_state(Some(FilesRead), authenticate)))
+        .route("/api/files/{file_id}", patch(update_file).delete(delete_file).layer(middleware::from_fn_with_state(Some(FilesWrite), authenticate)))
+        .route("/api/files/{file_id}/versions", get(list_versions).layer(middleware::from_fn_with_state(Some(FilesRead), authenticate)))
+        .route("/api/files/{file_id}/versions/{version}/download", get(download_version).layer(middleware::from_fn_with_state(Some(FilesRead), authenticate)))
+        .route("/api/files/{file_id}/versions/{version}/revert", post(revert_version).layer(middleware::from_fn_with_state(Some(FilesWrite), authenticate)))
+        .route("/api/folders", get(list_root).layer(middleware::from_fn_with_state(Some(FilesRead), authenticate))
+            .merge(post(create_folder).layer(middleware::from_fn_with_state(Some(FilesWrite), authenticate))))
+        .route("/api/folders/{folder_id}", get(get_folder).layer(middleware::from_fn_with_state(Some(FilesRead), authenticate))
+            .merge(patch(update_folder).delete(delete_folder).layer(middleware::from_fn_with_state(Some(FilesWrite), authenticate))))
+        .route("/api/trash", get(list_trash).layer(middleware::from_fn_with_state(Some(FilesRead), authenticate))
+            .merge(delete(empty_trash).layer(middleware::from_fn_with_state(Some(FilesWrite), authenticate))))
+        .route("/api/trash/{file_id}", delete(purge_file).layer(middleware::from_fn_with_state(Some(FilesWrite), authenticate)))
+        .route("/api/trash/{file_id}/restore", post(restore_file).layer(middleware::from_fn_with_state(Some(FilesWrite), authenticate)))
+        .route("/api/files/{file_id}/links", post(create_link).get(list_links).layer(middleware::from_fn_with_state(Some(SharesManage), authenticate)))
+        .route("/api/links/{link_id}", delete(revoke).layer(middleware::from_fn_with_state(Some(SharesManage), authenticate)))
+        .route("/api/tus", post(create_upload).layer(middleware::from_fn_with_state(Some(FilesWrite), authenticate)).options(tus_options))
+        .route("/api/tus/{upload_id}", head(upload_status).patch(upload_chunk).delete(terminate_upload).layer(middleware::from_fn_with_state(Some(FilesWrite), authenticate)));
     
+    // Checked before serving, an SMTP relay that does not work would otherwise only show when a mail is due.
+    if let Err(error) = mailer().await {
+        println!("Mailer unavailable: {}", error);
+        std::process::exit(1);
+    }
+
+    // Enforces VERSION_KEEP_LAST and VERSION_KEEP_DAYS on replaced file versions.
+    spawn_version_pruner();
+    // Removes tus uploads that did not move for TUS_UPLOAD_EXPIRY_SECONDS.
+    spawn_tus_upload_pruner();
+
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    // Peer addresses are recorded on login sessions.
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
 }
 
 async fn hello_world() -> &'static str{
-    "Hello World".as_ref()
+    "Hello World"
 }
 
 
 pub mod controller{
     pub mod usercontroller;
     pub mod filecontroller;
+    pub mod tuscontroller;
+    pub mod sharecontroller;
+    pub mod tokencontroller;
+    pub mod sessioncontroller;
+    pub mod apikeycontroller;
+    pub mod mfacontroller;
+    pub mod accountcontroller;
+    pub mod foldercontroller;
+    pub mod versioncontroller;
 }
 pub mod model{
     pub mod usermodel;
     pub mod filemodel;
     pub mod securitymodel;
+    pub mod storagemodel;
+    pub mod tusmodel;
+    pub mod sharemodel;
+    pub mod tokenmodel;
+    pub mod sessionmodel;
+    pub mod apikeymodel;
+    pub mod mfamodel;
+    pub mod throttlemodel;
+    pub mod mailmodel;
+    pub mod foldermodel;
+    pub mod versionmodel;
 }
 pub mod repository{
     pub mod userrepository;
     pub mod filerepository;
+    pub mod tusrepository;
+    pub mod sharerepository;
+    pub mod tokenrepository;
+    pub mod sessionrepository;
+    pub mod apikeyrepository;
+    pub mod mfarepository;
+    pub mod throttlerepository;
+    pub mod folderrepository;
+    pub mod versionrepository;
 }
 pub mod service{
     pub mod userservice;
     pub mod fileservice;
+    pub mod tusservice;
+    pub mod shareservice;
+    pub mod tokenservice;
+    pub mod sessionservice;
+    pub mod apikeyservice;
+    pub mod mfaservice;
+    pub mod throttleservice;
+    pub mod accountservice;
+    pub mod folderservice;
+    pub mod versionservice;
+}
+pub mod mail{
+    pub mod mailer;
+    pub mod smtpmailer;
+    pub mod filemailer;
+    pub mod logmailer;
+}
+pub mod storage{
+    pub mod storagebackend;
+    pub mod localstorage;
+    pub mod s3storage;
+    pub mod memorystorage;
 }
+#[allow(non_snake_case)]
 pub mod Security{
     pub mod jwt;
+    pub mod randomtoken;
+    pub mod password;
+    pub mod revocation;
+    pub mod totp;
+    pub mod filename;
 }
 pub mod schema;
 
@@ -0,0 +1,74 @@
+use chrono::NaiveDateTime;
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use crate::model::securitymodel::Scope;
+use crate::schema::api_key;
+
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = api_key)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ApiKey {
+    pub id: Option<i32>,
+    pub user_id: i32,
+    pub name: String,
+    pub prefix: String,
+    pub key_hash: String,
+    pub scopes: String,
+    pub created_at: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = api_key)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ApiKeyToInsert {
+    pub user_id: i32,
+    pub name: String,
+    pub prefix: String,
+    pub key_hash: String,
+    pub scopes: String,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    /// Lifetime of the key in seconds, the key never expires when omitted.
+    pub expires_in: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct ApiKeyResponse {
+    pub id: i32,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<Scope>,
+    pub created_at: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+impl From<ApiKey> for ApiKeyResponse {
+    fn from(key: ApiKey) -> Self {
+        ApiKeyResponse {
+            id: key.id.unwrap_or_default(),
+            scopes: Scope::parse_list(&key.scopes),
+            name: key.name,
+            prefix: key.prefix,
+            created_at: key.created_at,
+            last_used_at: key.last_used_at,
+            expires_at: key.expires_at,
+        }
+    }
+}
+
+/// Returned once on creation, the key cannot be retrieved later.
+#[derive(Serialize)]
+pub struct CreatedApiKeyResponse {
+    pub key: String,
+    #[serde(flatten)]
+    pub api_key: ApiKeyResponse,
+}
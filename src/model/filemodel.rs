@@ -1,4 +1,189 @@
+use chrono::NaiveDateTime;
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use crate::model::foldermodel::deserialize_explicit_null;
+use crate::model::sharemodel::ShareLink;
+use crate::model::usermodel::File;
+use crate::schema::blob;
+
 pub struct GetFileResponse{
     pub(crate) filename: String,
-     pub(crate) filepath: String
-}
\ No newline at end of file
+    pub(crate) filepath: String,
+    pub(crate) content_type: String,
+    pub(crate) content_hash: String,
+    pub(crate) hash_algorithm: Option<String>,
+    pub(crate) last_modified: Option<NaiveDateTime>,
+    /// The link the download came through, absent when the owner fetches a version directly.
+    pub(crate) share: Option<ShareLink>
+}
+
+/// Stored object shared by every file row with the same content digest.
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = blob)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Blob {
+    pub id: Option<i32>,
+    pub content_hash: String,
+    pub hash_algorithm: String,
+    pub storage_path: String,
+    pub size: i64,
+    pub ref_count: i32,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = blob)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct BlobToInsert {
+    pub content_hash: String,
+    pub hash_algorithm: String,
+    pub storage_path: String,
+    pub size: i64,
+}
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileSort {
+    Name,
+    Size,
+    #[default]
+    CreatedAt,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// Query string of `GET /api/files`. Dates are naive UTC timestamps like `2026-10-18T09:00:00`.
+#[derive(Deserialize, Default, Debug)]
+pub struct ListFilesQuery {
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+    #[serde(default)]
+    pub sort: FileSort,
+    #[serde(default)]
+    pub order: SortOrder,
+    pub content_type: Option<String>,
+    pub min_size: Option<i64>,
+    pub max_size: Option<i64>,
+    pub created_after: Option<NaiveDateTime>,
+    pub created_before: Option<NaiveDateTime>,
+    pub is_public: Option<bool>,
+}
+
+/// What an upload does when its folder already holds a file of the same name.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Refuse the upload with 409.
+    #[default]
+    Reject,
+    /// Store it as `name (1).ext`, `name (2).ext` and so on.
+    Rename,
+    /// Replace the content of the existing file, its id and share links stay.
+    Version,
+}
+
+impl ConflictPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConflictPolicy::Reject => "reject",
+            ConflictPolicy::Rename => "rename",
+            ConflictPolicy::Version => "version",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<ConflictPolicy> {
+        match value {
+            "reject" => Some(ConflictPolicy::Reject),
+            "rename" => Some(ConflictPolicy::Rename),
+            "version" => Some(ConflictPolicy::Version),
+            _ => None,
+        }
+    }
+}
+
+/// Query string of `POST /api/upload`.
+#[derive(Deserialize, Default, Debug)]
+pub struct UploadQuery {
+    /// Folder the uploaded files are put into, the top level when omitted.
+    pub folder_id: Option<i32>,
+    #[serde(default)]
+    pub on_conflict: ConflictPolicy,
+}
+
+/// Renames and/or moves a file, fields left out stay as they are. `"folder_id": null` moves it to the top level.
+#[derive(Deserialize, Debug)]
+pub struct UpdateFileRequest {
+    pub file_name: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_explicit_null")]
+    pub folder_id: Option<Option<i32>>,
+}
+
+/// Position after the last returned file: its sort value plus the id as tie breaker.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FileCursor {
+    pub id: i32,
+    pub name: Option<String>,
+    pub size: Option<i64>,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+/// Filters and page position handed to the repository, already validated by the service.
+pub struct FileListFilter {
+    pub owner_id: i32,
+    pub sort: FileSort,
+    pub order: SortOrder,
+    pub after: Option<FileCursor>,
+    pub limit: i64,
+    pub content_type: Option<String>,
+    pub min_size: Option<i64>,
+    pub max_size: Option<i64>,
+    pub created_after: Option<NaiveDateTime>,
+    pub created_before: Option<NaiveDateTime>,
+    pub is_public: Option<bool>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FileResponse {
+    pub id: i32,
+    pub file_name: String,
+    pub content_type: String,
+    pub size: i64,
+    pub content_hash: String,
+    pub is_public: bool,
+    pub folder_id: Option<i32>,
+    pub version: i32,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+    /// Set while the file sits in the trash.
+    pub deleted_at: Option<NaiveDateTime>,
+}
+
+impl From<File> for FileResponse {
+    fn from(listed_file: File) -> Self {
+        FileResponse {
+            id: listed_file.id.unwrap_or_default(),
+            file_name: listed_file.file_name,
+            content_type: listed_file.content_type,
+            size: listed_file.size,
+            content_hash: listed_file.content_hash,
+            is_public: listed_file.is_public == Some(1),
+            folder_id: listed_file.folder_id,
+            version: listed_file.version,
+            created_at: listed_file.created_at,
+            updated_at: listed_file.updated_at,
+            deleted_at: listed_file.deleted_at,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct FileListResponse {
+    pub files: Vec<FileResponse>,
+    /// Pass back as `cursor` to fetch the next page, absent on the last page.
+    pub next_cursor: Option<String>,
+}
@@ -0,0 +1,105 @@
+use chrono::NaiveDateTime;
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Deserializer, Serialize};
+use crate::model::filemodel::FileResponse;
+use crate::schema::folder;
+
+#[derive(Queryable, Selectable, Debug, Clone)]
+#[diesel(table_name = folder)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Folder {
+    pub id: Option<i32>,
+    pub owner_id: i32,
+    pub parent_id: Option<i32>,
+    pub name: String,
+    /// `/parent/child`, kept in sync with the names of all ancestors.
+    pub path: String,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = folder)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct FolderToInsert {
+    pub owner_id: i32,
+    pub parent_id: Option<i32>,
+    pub name: String,
+    pub path: String,
+}
+
+/// `/parent/child` for a folder named `name` inside `parent`, or at the top level.
+pub fn folder_path(parent: Option<&Folder>, name: &str) -> String {
+    format!("{}/{}", parent.map_or("", |parent| parent.path.as_str()), name)
+}
+
+/// What the repository found when it went to rename or move a folder.
+pub enum FolderUpdate {
+    Updated(Folder),
+    FolderMissing,
+    ParentMissing,
+    /// The new parent is the folder itself or lies below it.
+    IntoItself,
+}
+
+/// Tells an explicit `null` apart from a missing field: `Some(None)` moves to the top level, `None` keeps the parent.
+pub fn deserialize_explicit_null<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Deserialize)]
+pub struct CreateFolderRequest {
+    pub name: String,
+    /// Created at the top level when omitted.
+    pub parent_id: Option<i32>,
+}
+
+/// Renames and/or moves a folder, fields left out stay as they are.
+#[derive(Deserialize)]
+pub struct UpdateFolderRequest {
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_explicit_null")]
+    pub parent_id: Option<Option<i32>>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct DeleteFolderQuery {
+    /// Also deletes subfolders and moves the contained files into the trash.
+    #[serde(default)]
+    pub recursive: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FolderResponse {
+    pub id: i32,
+    pub name: String,
+    pub parent_id: Option<i32>,
+    pub path: String,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+impl From<Folder> for FolderResponse {
+    fn from(other_folder: Folder) -> Self {
+        FolderResponse {
+            id: other_folder.id.unwrap_or_default(),
+            name: other_folder.name,
+            parent_id: other_folder.parent_id,
+            path: other_folder.path,
+            created_at: other_folder.created_at,
+            updated_at: other_folder.updated_at,
+        }
+    }
+}
+
+/// Direct children of a folder, or of the top level when `folder` is absent.
+#[derive(Serialize, Debug)]
+pub struct FolderListingResponse {
+    pub folder: Option<FolderResponse>,
+    pub folders: Vec<FolderResponse>,
+    pub files: Vec<FileResponse>,
+}
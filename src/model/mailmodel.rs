@@ -0,0 +1,60 @@
+use std::fmt;
+use std::fmt::Formatter;
+use serde::Deserialize;
+use crate::model::usermodel::ConversionError;
+
+/// A plain text email.
+#[derive(Debug, Clone)]
+pub struct MailMessage {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug)]
+pub enum MailError {
+    InvalidAddress(String),
+    Backend(String),
+}
+
+impl fmt::Display for MailError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MailError::InvalidAddress(address) => write!(f, "Invalid email address: {}", address),
+            MailError::Backend(message) => write!(f, "Mailer error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for MailError {}
+
+impl From<std::io::Error> for MailError {
+    fn from(value: std::io::Error) -> Self {
+        MailError::Backend(value.to_string())
+    }
+}
+
+impl From<MailError> for ConversionError {
+    fn from(value: MailError) -> Self {
+        match value {
+            MailError::InvalidAddress(_) => ConversionError::BadRequest(value.to_string()),
+            _ => ConversionError::ConversionError(value.to_string()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
+
+#[derive(Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+#[derive(Deserialize)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    pub password: String,
+}
@@ -0,0 +1,67 @@
+use chrono::NaiveDateTime;
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use crate::schema::{mfa_recovery_code, user_mfa};
+
+/// TOTP enrollment of a user. Unconfirmed rows are replaced when enrollment is started again.
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = user_mfa)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct UserMfa {
+    pub user_id: i32,
+    pub totp_secret: String,
+    pub confirmed_at: Option<NaiveDateTime>,
+    pub last_used_step: Option<i64>,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = user_mfa)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct UserMfaToInsert {
+    pub user_id: i32,
+    pub totp_secret: String,
+}
+
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = mfa_recovery_code)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct RecoveryCode {
+    pub id: Option<i32>,
+    pub user_id: i32,
+    pub code_hash: String,
+    pub used_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = mfa_recovery_code)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct RecoveryCodeToInsert {
+    pub user_id: i32,
+    pub code_hash: String,
+}
+
+#[derive(Serialize)]
+pub struct TotpEnrollmentResponse {
+    /// Base32 secret for manual entry.
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+/// A TOTP code or, where accepted, one of the recovery codes.
+#[derive(Deserialize)]
+pub struct MfaCodeRequest {
+    pub code: String,
+}
+
+#[derive(Deserialize)]
+pub struct MfaLoginRequest {
+    pub mfa_token: String,
+    pub code: String,
+}
+
+/// Shown once, only their hashes are stored.
+#[derive(Serialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
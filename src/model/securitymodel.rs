@@ -1,15 +1,177 @@
 use std::fmt;
-use std::fmt::{write, Formatter};
+use std::fmt::Formatter;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, NaiveDateTime};
 use serde::{Deserialize, Serialize};
 use crate::model::usermodel;
+use crate::model::usermodel::User;
 
 
 #[derive(Deserialize, Serialize)]
 pub struct EncodeJWT{
+    /// Id of the user the token was issued to.
+    pub(crate) sub: String,
     pub(crate) username: String,
-    pub(crate) email: String
+    pub(crate) email: String,
+    pub(crate) iss: String,
+    pub(crate) iat: i64,
+    pub(crate) exp: i64,
+    /// Unique id of this token.
+    pub(crate) jti: String,
+    /// Session the token belongs to, absent on tokens issued before sessions were recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) sid: Option<i32>,
+    /// Space separated scopes, absent on tokens issued before scopes existed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) scope: Option<String>,
+}
+
+/// Claims of the single purpose tokens: MFA challenges, email verification and password reset.
+/// They carry an audience, which access token validation rejects, so none can be used as one.
+#[derive(Deserialize, Serialize)]
+pub struct PurposeClaims {
+    pub(crate) sub: String,
+    pub(crate) aud: String,
+    pub(crate) iss: String,
+    pub(crate) iat: i64,
+    pub(crate) exp: i64,
+    pub(crate) jti: String,
+    /// Digest of state the token depends on, e.g. the password hash for resets, so it dies once that changes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) fpr: Option<String>,
+}
+
+/// Permission a credential carries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Scope {
+    #[serde(rename = "files:read")]
+    FilesRead,
+    #[serde(rename = "files:write")]
+    FilesWrite,
+    #[serde(rename = "shares:manage")]
+    SharesManage,
+    #[serde(rename = "admin")]
+    Admin,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 4] = [Scope::FilesRead, Scope::FilesWrite, Scope::SharesManage, Scope::Admin];
+
+    /// What a regular login may do, `admin` is never granted implicitly.
+    pub fn user_default() -> Vec<Scope> {
+        vec![Scope::FilesRead, Scope::FilesWrite, Scope::SharesManage]
+    }
+
+    /// Scopes a login of `user` gets, the defaults plus `admin` for admins.
+    pub fn for_user(user: &User) -> Vec<Scope> {
+        let mut scopes = Scope::user_default();
+        if user.is_admin == 1 {
+            scopes.push(Scope::Admin);
+        }
+        scopes
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::FilesRead => "files:read",
+            Scope::FilesWrite => "files:write",
+            Scope::SharesManage => "shares:manage",
+            Scope::Admin => "admin",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Scope> {
+        Scope::ALL.into_iter().find(|scope| scope.as_str() == value)
+    }
+
+    /// Scopes are stored space separated, unknown entries are dropped.
+    pub fn parse_list(value: &str) -> Vec<Scope> {
+        value.split_whitespace().filter_map(Scope::parse).collect()
+    }
+
+    pub fn join(scopes: &[Scope]) -> String {
+        scopes.iter().map(Scope::as_str).collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// How the request proved who it is.
+#[derive(Clone, Debug)]
+pub enum Credential {
+    /// Bearer access token. `token_id` is its `jti`, needed to revoke it on logout.
+    Jwt { token_id: String, expires_at: NaiveDateTime, session_id: Option<i32> },
+    ApiKey { key_id: i32 },
+}
+
+/// The user behind the request's credential, resolved once by the `authenticate` middleware.
+#[derive(Clone, Debug)]
+pub struct AuthUser {
+    pub id: i32,
+    pub name: String,
+    pub email: String,
+    pub credential: Credential,
+    pub scopes: Vec<Scope>,
+}
+
+impl AuthUser {
+    pub fn from_token(user: User, claims: &EncodeJWT) -> Result<Self, AuthError> {
+        let id = user.id.ok_or_else(|| AuthError::AuthError("User has no id".to_string(), StatusCode::INTERNAL_SERVER_ERROR))?;
+        let expires_at = DateTime::from_timestamp(claims.exp, 0)
+            .ok_or_else(|| AuthError::AuthError("Invalid expiry in JWT Token".to_string(), StatusCode::UNAUTHORIZED))?
+            .naive_utc();
+        Ok(AuthUser {
+            id,
+            name: user.name,
+            email: user.email,
+            credential: Credential::Jwt { token_id: claims.jti.clone(), expires_at, session_id: claims.sid },
+            scopes: claims.scope.as_deref().map(Scope::parse_list).unwrap_or_else(Scope::user_default),
+        })
+    }
+
+    pub fn from_api_key(user: User, key_id: i32, scopes: Vec<Scope>) -> Result<Self, AuthError> {
+        let id = user.id.ok_or_else(|| AuthError::AuthError("User has no id".to_string(), StatusCode::INTERNAL_SERVER_ERROR))?;
+        Ok(AuthUser { id, name: user.name, email: user.email, credential: Credential::ApiKey { key_id }, scopes })
+    }
+
+    /// `admin` satisfies every requirement.
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes.contains(&scope) || self.scopes.contains(&Scope::Admin)
+    }
+
+    /// Rejects credentials that were not granted `scope`.
+    pub fn require_scope(&self, scope: Scope) -> Result<(), AuthError> {
+        if !self.has_scope(scope) {
+            return Err(AuthError::AuthError(format!("Missing scope {}", scope.as_str()), StatusCode::FORBIDDEN))
+        }
+        Ok(())
+    }
+
+    /// Account management needs a login token, a leaked API key must not reach it.
+    pub fn require_login(&self) -> Result<(), usermodel::ConversionError> {
+        if !matches!(self.credential, Credential::Jwt { .. }) {
+            return Err(usermodel::ConversionError::Forbidden("Requires a login token, API keys are not accepted".to_string()))
+        }
+        Ok(())
+    }
+
+    pub fn session_id(&self) -> Option<i32> {
+        match self.credential {
+            Credential::Jwt { session_id, .. } => session_id,
+            Credential::ApiKey { .. } => None,
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for AuthUser {
+    type Rejection = AuthError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts.extensions.get::<AuthUser>()
+            .cloned()
+            .ok_or_else(|| AuthError::AuthError("Route is not behind authenticate".to_string(), StatusCode::UNAUTHORIZED))
+    }
 }
 
 #[derive(Debug)]
@@ -32,13 +194,18 @@ impl std::error::Error for AuthError{
 
 impl From<usermodel::ConversionError> for AuthError {
     fn from(err: usermodel::ConversionError) -> Self {
-        
-        AuthError::AuthError("Error".to_string(), StatusCode::INTERNAL_SERVER_ERROR)
+        match err {
+            usermodel::ConversionError::Unauthorized(message) => AuthError::AuthError(message, StatusCode::UNAUTHORIZED),
+            usermodel::ConversionError::Forbidden(message) => AuthError::AuthError(message, StatusCode::FORBIDDEN),
+            _ => AuthError::AuthError("Error".to_string(), StatusCode::INTERNAL_SERVER_ERROR),
+        }
     }
 }
 
 impl IntoResponse for AuthError{
     fn into_response(self) -> Response {
-        StatusCode::FORBIDDEN.into_response()
+        match self {
+            AuthError::AuthError(message, status) => (status, message).into_response()
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,64 @@
+use chrono::NaiveDateTime;
+use diesel::{Insertable, Queryable, Selectable};
+use serde::Serialize;
+use crate::schema::session;
+
+/// One login of a user, alive as long as its refresh token family.
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = session)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Session {
+    pub id: Option<i32>,
+    pub user_id: i32,
+    pub family_id: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub last_seen_at: Option<NaiveDateTime>,
+    pub expires_at: NaiveDateTime,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = session)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct SessionToInsert {
+    pub user_id: i32,
+    pub family_id: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub expires_at: NaiveDateTime,
+}
+
+/// Where a login came from, recorded on its session.
+#[derive(Debug, Default, Clone)]
+pub struct SessionClient {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SessionResponse {
+    pub id: i32,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub last_seen_at: Option<NaiveDateTime>,
+    pub expires_at: NaiveDateTime,
+    /// The session the listing request itself was made with.
+    pub current: bool,
+}
+
+impl SessionResponse {
+    pub fn new(session: Session, current_session: Option<i32>) -> Self {
+        SessionResponse {
+            id: session.id.unwrap_or_default(),
+            current: session.id.is_some() && session.id == current_session,
+            user_agent: session.user_agent,
+            ip_address: session.ip_address,
+            created_at: session.created_at,
+            last_seen_at: session.last_seen_at,
+            expires_at: session.expires_at,
+        }
+    }
+}
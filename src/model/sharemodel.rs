@@ -0,0 +1,72 @@
+use chrono::NaiveDateTime;
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use crate::schema::file_to_link;
+
+#[derive(Queryable, Selectable, Debug, Clone)]
+#[diesel(table_name = file_to_link)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ShareLink {
+    pub id: Option<i32>,
+    pub link: Option<String>,
+    pub filename: Option<String>,
+    pub file_id: Option<i32>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub password_hash: Option<String>,
+    pub max_downloads: Option<i32>,
+    pub download_count: i32,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub created_by: Option<i32>,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = file_to_link)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ShareLinkToInsert {
+    pub link: String,
+    pub filename: String,
+    pub file_id: i32,
+    pub expires_at: Option<NaiveDateTime>,
+    pub password_hash: Option<String>,
+    pub max_downloads: Option<i32>,
+    pub created_by: Option<i32>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Deserialize, Default)]
+pub struct CreateShareLinkRequest {
+    /// Lifetime of the link in seconds, the link never expires when omitted.
+    pub expires_in: Option<i64>,
+    pub password: Option<String>,
+    pub max_downloads: Option<i32>,
+}
+
+#[derive(Serialize)]
+pub struct ShareLinkResponse {
+    pub id: i32,
+    pub url: String,
+    pub file_id: Option<i32>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub password_protected: bool,
+    pub max_downloads: Option<i32>,
+    pub download_count: i32,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+impl From<ShareLink> for ShareLinkResponse {
+    fn from(share: ShareLink) -> Self {
+        ShareLinkResponse {
+            id: share.id.unwrap_or_default(),
+            url: format!("localhost:3000/api/download/{}", share.link.unwrap_or_default()),
+            file_id: share.file_id,
+            expires_at: share.expires_at,
+            password_protected: share.password_hash.is_some(),
+            max_downloads: share.max_downloads,
+            download_count: share.download_count,
+            revoked_at: share.revoked_at,
+            created_at: share.created_at,
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::fmt;
+use std::fmt::Formatter;
+use chrono::NaiveDateTime;
+use crate::model::usermodel::ConversionError;
+
+#[derive(Debug, Clone)]
+pub struct ObjectMeta {
+    pub key: String,
+    pub size: u64,
+    pub last_modified: Option<NaiveDateTime>,
+}
+
+/// Inclusive byte range, as used by HTTP `Range` requests.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    pub fn length(&self) -> u64 {
+        self.end - self.start + 1
+    }
+}
+
+#[derive(Debug)]
+pub enum StorageError {
+    NotFound(String),
+    InvalidKey(String),
+    TooLarge(u64),
+    Backend(String),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::NotFound(key) => write!(f, "Object not found: {}", key),
+            StorageError::InvalidKey(key) => write!(f, "Invalid storage key: {}", key),
+            StorageError::TooLarge(limit) => write!(f, "Upload exceeds the limit of {} bytes", limit),
+            StorageError::Backend(message) => write!(f, "Storage backend error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+impl From<std::io::Error> for StorageError {
+    fn from(value: std::io::Error) -> Self {
+        StorageError::Backend(value.to_string())
+    }
+}
+
+/// Storage keys are internal, the messages that carry one are logged and replaced before they reach a client.
+impl From<StorageError> for ConversionError {
+    fn from(value: StorageError) -> Self {
+        match value {
+            StorageError::TooLarge(_) => ConversionError::PayloadTooLarge(value.to_string()),
+            StorageError::NotFound(_) => ConversionError::NotFound("File not found".to_string()),
+            _ => {
+                println!("Storage Error: {}", value);
+                ConversionError::ConversionError("Storage Error".to_string())
+            }
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use chrono::NaiveDateTime;
+use diesel::{Insertable, Queryable, Selectable};
+use crate::model::usermodel::User;
+use crate::schema::{login_lockout, login_throttle};
+
+/// Failed login attempts counted against one account name or one client address.
+#[derive(Queryable, Selectable, Debug, Clone, PartialEq)]
+#[diesel(table_name = login_throttle)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct LoginThrottle {
+    /// `account:<user id>`, `identifier:<unknown login>`, `share:<link id>`, `share:<link id>:<address>` or
+    /// `ip:<address>`, see `LoginThrottleKeys`.
+    /// Rows written before logins were resolved to user ids still use `account:<name>`, they age out with the window.
+    pub throttle_key: String,
+    pub failures: i32,
+    pub last_failure_at: NaiveDateTime,
+    pub blocked_until: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = login_lockout)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct LoginLockoutToInsert {
+    pub throttle_key: String,
+    pub ip_address: Option<String>,
+    pub failures: i32,
+    pub locked_until: NaiveDateTime,
+}
+
+/// Counters a login attempt is checked against and charged to.
+#[derive(Debug, Clone)]
+pub struct LoginThrottleKeys {
+    pub account: String,
+    pub ip_address: Option<String>,
+    /// Counter for the address, `None` when the address is unknown.
+    ip_key: Option<String>,
+}
+
+impl LoginThrottleKeys {
+    /// Known accounts are counted by id, so guesses via name and email share one budget. Unknown
+    /// identifiers get a key of their own and are throttled exactly like existing accounts, the
+    /// responses do not reveal which exist.
+    pub fn new(identifier: &str, stored_user: Option<&User>, ip_address: Option<&str>) -> Self {
+        let account = match stored_user.and_then(|user| user.id) {
+            Some(user_id) => format!("account:{}", user_id),
+            None => format!("identifier:{}", identifier.trim().to_lowercase()),
+        };
+        LoginThrottleKeys {
+            account,
+            ip_address: ip_address.map(str::to_string),
+            ip_key: ip_address.map(|ip| format!("ip:{}", ip)),
+        }
+    }
+
+    /// The login budget of an account whose user is already known, for checks made after signing in.
+    pub fn for_account(user_id: i32, ip_address: Option<&str>) -> Self {
+        LoginThrottleKeys {
+            account: format!("account:{}", user_id),
+            ip_address: ip_address.map(str::to_string),
+            ip_key: ip_address.map(|ip| format!("ip:{}", ip)),
+        }
+    }
+
+    /// Password guesses at a protected share link, charged to the link and to the address at that link. The
+    /// address counter is separate from the login one, so guessing at a link never locks anyone out of logging in.
+    pub fn for_share_link(link_id: i32, ip_address: Option<&str>) -> Self {
+        LoginThrottleKeys {
+            account: format!("share:{}", link_id),
+            ip_address: ip_address.map(str::to_string),
+            ip_key: ip_address.map(|ip| format!("share:{}:{}", link_id, ip)),
+        }
+    }
+
+    pub fn ip(&self) -> Option<String> {
+        self.ip_key.clone()
+    }
+}
+
+/// One key an attempt is counted against, with the policy values the repository needs inside its transaction.
+pub struct ThrottleCharge {
+    pub key: String,
+    /// A count last touched before this starts over.
+    pub restart_before: NaiveDateTime,
+    /// A count that reached this and whose block ran out starts over.
+    pub restart_from: i32,
+    /// Until when the key is blocked once the attempt brought it to the given count.
+    pub block_after: Box<dyn FnOnce(i32) -> Option<NaiveDateTime> + Send>,
+}
+
+/// A key's row before and after an attempt was counted against it.
+#[derive(Debug, Clone)]
+pub struct ReservedThrottle {
+    /// `None` when the key had no row yet.
+    pub previous: Option<LoginThrottle>,
+    pub counted: LoginThrottle,
+}
+
+pub enum ThrottleReservation {
+    Reserved(Vec<ReservedThrottle>),
+    /// Nothing was counted, one of the keys is blocked until then.
+    Blocked(NaiveDateTime),
+}
+
+/// An attempt that is already counted as a failure. It is settled with `record_login_failure`,
+/// `record_login_success` or `refund_login_attempt` once the credentials were checked.
+#[derive(Debug)]
+pub struct LoginAttempt {
+    pub keys: LoginThrottleKeys,
+    pub reserved: Vec<ReservedThrottle>,
+}
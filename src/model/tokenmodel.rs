@@ -0,0 +1,48 @@
+use chrono::NaiveDateTime;
+use diesel::{Insertable, Queryable, Selectable};
+use serde::Deserialize;
+use crate::schema::{refresh_token, revoked_token};
+
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = refresh_token)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct RefreshToken {
+    pub id: Option<i32>,
+    pub user_id: i32,
+    pub token_hash: String,
+    pub family_id: String,
+    pub expires_at: NaiveDateTime,
+    pub created_at: Option<NaiveDateTime>,
+    pub used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = refresh_token)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct RefreshTokenToInsert {
+    pub user_id: i32,
+    pub token_hash: String,
+    pub family_id: String,
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = revoked_token)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct RevokedTokenToInsert {
+    pub jti: String,
+    pub user_id: i32,
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Deserialize, Default)]
+pub struct LogoutRequest {
+    /// Also revokes the refresh token and every token rotated from the same login.
+    pub refresh_token: Option<String>,
+}
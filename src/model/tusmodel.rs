@@ -0,0 +1,121 @@
+use std::fmt;
+use std::fmt::Formatter;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use chrono::NaiveDateTime;
+use diesel::{Insertable, Queryable, Selectable};
+use crate::model::storagemodel::StorageError;
+use crate::model::usermodel::ConversionError;
+use crate::schema::{tus_upload, tus_upload_part};
+
+pub const TUS_VERSION: &str = "1.0.0";
+pub const TUS_EXTENSIONS: &str = "creation,termination,checksum";
+pub const TUS_CHECKSUM_ALGORITHMS: &str = "sha1,sha256";
+
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = tus_upload)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct TusUpload {
+    pub id: Option<i32>,
+    pub upload_id: String,
+    pub file_name: String,
+    pub content_type: Option<String>,
+    pub upload_length: i64,
+    pub upload_offset: i64,
+    pub owner_id: Option<i32>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+    /// `ConflictPolicy` applied when the finished upload is registered.
+    pub on_conflict: String,
+    /// Folder the finished upload is registered into.
+    pub folder_id: Option<i32>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = tus_upload)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct TusUploadToInsert {
+    pub upload_id: String,
+    pub file_name: String,
+    pub content_type: Option<String>,
+    pub upload_length: i64,
+    pub owner_id: Option<i32>,
+    pub on_conflict: String,
+    pub folder_id: Option<i32>,
+}
+
+/// One stored PATCH body of an upload.
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = tus_upload_part)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct TusUploadPart {
+    pub id: Option<i32>,
+    pub upload_id: String,
+    pub part_offset: i64,
+    pub part_key: String,
+    pub size: i64,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = tus_upload_part)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct TusUploadPartToInsert {
+    pub upload_id: String,
+    pub part_offset: i64,
+    pub part_key: String,
+    pub size: i64,
+}
+
+/// Result of a PATCH request: the new offset and, once the last chunk arrived, the download link.
+pub struct TusPatchResult {
+    pub upload_offset: i64,
+    pub file_link: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum TusError {
+    TusError(String, StatusCode)
+}
+
+impl TusError {
+    pub fn new(message: &str, status: StatusCode) -> Self {
+        TusError::TusError(message.to_string(), status)
+    }
+}
+
+impl fmt::Display for TusError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TusError::TusError(message, status) => write!(f, "Error: {},StatusCode: {}", message, status)
+        }
+    }
+}
+
+impl std::error::Error for TusError {}
+
+impl From<ConversionError> for TusError {
+    fn from(err: ConversionError) -> Self {
+        let status = match err {
+            ConversionError::NotFound(_) => StatusCode::NOT_FOUND,
+            ConversionError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            ConversionError::Conflict(_) => StatusCode::CONFLICT,
+            ConversionError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        TusError::TusError(err.to_string(), status)
+    }
+}
+
+impl From<StorageError> for TusError {
+    fn from(err: StorageError) -> Self {
+        TusError::from(ConversionError::from(err))
+    }
+}
+
+impl IntoResponse for TusError {
+    fn into_response(self) -> Response {
+        match self {
+            TusError::TusError(message, status) => (status, [("Tus-Resumable", TUS_VERSION), ("Tus-Version", TUS_VERSION)], message).into_response()
+        }
+    }
+}
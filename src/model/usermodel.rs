@@ -1,10 +1,9 @@
 use std::env::VarError;
 use std::fmt;
 use std::fmt::Formatter;
-use std::io::Error;
 use std::num::TryFromIntError;
 use axum::extract::multipart::MultipartError;
-use axum::http::StatusCode;
+use axum::http::{header, StatusCode};
 use axum::Json;
 use axum::response::{IntoResponse, Response};
 use bcrypt::BcryptError;
@@ -13,7 +12,6 @@ use diesel::{Insertable, Queryable, Selectable};
 use serde::{Deserialize, Serialize};
 use tokio::task::JoinError;
 use crate::schema::*;
-use crate::schema::users::email;
 
 #[derive(Queryable, Selectable, Serialize, Deserialize, Debug)]
 #[diesel(table_name = users)]
@@ -23,6 +21,9 @@ pub struct User{
     pub name: String,    // Assuming users.name -> Text
     pub password: String,// Assuming users.password -> Text
     pub email: String,   // Assuming users.email -> Text
+    pub tokens_valid_after: Option<NaiveDateTime>, // set by revoke-all, older access tokens are rejected
+    pub is_admin: i32, // 1 grants the admin scope on login
+    pub email_verified_at: Option<NaiveDateTime>, // set once the user followed the verification mail
 }
 #[derive(Insertable)]
 #[derive(Deserialize, Serialize)]
@@ -33,24 +34,53 @@ pub struct CreateUserRequest{
     pub password: String,
     pub email: String,
 }
-#[derive(Serialize,Deserialize,Queryable, Clone)]
+#[derive(Serialize,Deserialize, Clone)]
 pub struct LoginRequest{
-    pub name:String,
+    /// User name or email address. Clients from before email logins send it as `name`.
+    #[serde(alias = "name")]
+    pub identifier: String,
     pub password: String,
-    pub email: String
 }
 
 pub struct LoginResponse{
     pub status_code: StatusCode,
-    pub jwt_token: String
+    pub jwt_token: String,
+    pub refresh_token: String,
+    /// Seconds until `jwt_token` expires.
+    pub expires_in: i64,
 }
 
 impl IntoResponse for LoginResponse{
     fn into_response(self) -> Response {
         let res_json = serde_json::json!({
             "token" : self.jwt_token,
+            "token_type" : "Bearer",
+            "expires_in" : self.expires_in,
+            "refresh_token" : self.refresh_token,
         });
-        (StatusCode::OK, Json(res_json)).into_response()
+        (self.status_code, Json(res_json)).into_response()
+    }
+}
+
+/// What the first login step hands out: tokens, or a challenge when the account has MFA enabled.
+pub enum LoginOutcome {
+    Tokens(LoginResponse),
+    MfaRequired { mfa_token: String, expires_in: i64 },
+}
+
+impl IntoResponse for LoginOutcome {
+    fn into_response(self) -> Response {
+        match self {
+            LoginOutcome::Tokens(response) => response.into_response(),
+            LoginOutcome::MfaRequired { mfa_token, expires_in } => {
+                let res_json = serde_json::json!({
+                    "mfa_required" : true,
+                    "mfa_token" : mfa_token,
+                    "expires_in" : expires_in,
+                });
+                (StatusCode::OK, Json(res_json)).into_response()
+            }
+        }
     }
 }
 
@@ -63,7 +93,7 @@ pub struct File {
     pub hashed_file_name: String,
     pub content_hash: String,
     pub content_type: String,
-    pub size: i32, // `size -> Integer`
+    pub size: i64, // `size -> BigInt`, SQLite INTEGER is 64 bit
     pub storage_path: String,
     pub owner_id: Option<i32>, // `owner_id -> Nullable<Integer>`
     pub is_public: Option<i32>, // `is_public -> Nullable<Integer>`
@@ -71,9 +101,11 @@ pub struct File {
     pub created_at: Option<NaiveDateTime>, // <--- THIS IS THE FIX
     pub updated_at: Option<NaiveDateTime>, // <--- THIS IS THE FIX
     pub deleted_at: Option<NaiveDateTime>, // <--- THIS IS THE FIX
+    pub folder_id: Option<i32>, // NULL for files at the top level
+    pub version: i32, // number of the current content, earlier ones are in file_version
 }
 
-#[derive(Insertable, Deserialize, Serialize, Debug)]
+#[derive(Insertable, Deserialize, Serialize, Debug, Clone)]
 #[diesel(table_name = file)]
 #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
 pub struct FileToInsert {
@@ -81,24 +113,42 @@ pub struct FileToInsert {
     pub hashed_file_name: String,
     pub content_hash: String,
     pub content_type: String,
-    pub size: i32,
+    pub size: i64,
     pub storage_path: String,
     pub owner_id: Option<i32>,
     pub is_public: Option<i32>,
     pub is_deleted: Option<i32>,
+    pub folder_id: Option<i32>,
     // Timestamps are omitted here because your SQL schema has DEFAULT CURRENT_TIMESTAMP for them,
     // so Diesel will not try to insert them, relying on the DB to set them.
 }
 
 #[derive(Debug)]
 pub enum ConversionError {
-    ConversionError(String)
+    ConversionError(String),
+    PayloadTooLarge(String),
+    NotFound(String),
+    Conflict(String),
+    Gone(String),
+    Unauthorized(String),
+    Forbidden(String),
+    BadRequest(String),
+    /// Seconds the client has to wait, sent as `Retry-After`.
+    TooManyRequests(String, i64)
 }
 
 impl fmt::Display for ConversionError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            ConversionError::ConversionError(message) => write!(f,"Conversion Error {} ", message)
+            ConversionError::ConversionError(message) => write!(f,"Conversion Error {} ", message),
+            ConversionError::PayloadTooLarge(message) => write!(f,"Payload Too Large {} ", message),
+            ConversionError::NotFound(message) => write!(f,"Not Found {} ", message),
+            ConversionError::Conflict(message) => write!(f,"Conflict {} ", message),
+            ConversionError::Gone(message) => write!(f,"Gone {} ", message),
+            ConversionError::Unauthorized(message) => write!(f,"Unauthorized {} ", message),
+            ConversionError::Forbidden(message) => write!(f,"Forbidden {} ", message),
+            ConversionError::BadRequest(message) => write!(f,"Bad Request {} ", message),
+            ConversionError::TooManyRequests(message, retry_after) => write!(f,"Too Many Requests {}, retry after {} seconds ", message, retry_after)
         }
     }
 }
@@ -119,16 +169,26 @@ impl From<BcryptError> for ConversionError{
 }
 impl IntoResponse for ConversionError{
     fn into_response(self) -> Response {
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Erro with Storing File and Provide Link: {}", self)).into_response()
+        match self {
+            ConversionError::PayloadTooLarge(_) => (StatusCode::PAYLOAD_TOO_LARGE, self.to_string()).into_response(),
+            ConversionError::NotFound(_) => (StatusCode::NOT_FOUND, self.to_string()).into_response(),
+            ConversionError::Conflict(_) => (StatusCode::CONFLICT, self.to_string()).into_response(),
+            ConversionError::Gone(_) => (StatusCode::GONE, self.to_string()).into_response(),
+            ConversionError::Unauthorized(_) => (StatusCode::UNAUTHORIZED, self.to_string()).into_response(),
+            ConversionError::Forbidden(_) => (StatusCode::FORBIDDEN, self.to_string()).into_response(),
+            ConversionError::BadRequest(_) => (StatusCode::BAD_REQUEST, self.to_string()).into_response(),
+            ConversionError::TooManyRequests(_, retry_after) => (StatusCode::TOO_MANY_REQUESTS, [(header::RETRY_AFTER, retry_after.to_string())], self.to_string()).into_response(),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, format!("Erro with Storing File and Provide Link: {}", self)).into_response()
+        }
     }
 }
 impl From<MultipartError> for ConversionError {
-    fn from(err: MultipartError) -> Self {
+    fn from(_err: MultipartError) -> Self {
         ConversionError::ConversionError("Erorr".to_string())
     }
 }
 impl From<VarError> for ConversionError{
-    fn from(value: VarError) -> Self {
+    fn from(_value: VarError) -> Self {
         ConversionError::ConversionError("Error Converting stuff".to_string())
     }
 }
@@ -140,7 +200,7 @@ impl From<JoinError> for ConversionError{
 }
 
 impl From<Box<dyn std::error::Error>> for ConversionError{
-    fn from(value: Box<dyn std::error::Error>) -> Self {
+    fn from(_value: Box<dyn std::error::Error>) -> Self {
         ConversionError::ConversionError("Error".to_string())
     }
 }
\ No newline at end of file
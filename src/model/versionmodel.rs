@@ -0,0 +1,92 @@
+use chrono::NaiveDateTime;
+use diesel::{Insertable, Queryable, Selectable};
+use serde::Serialize;
+use crate::model::usermodel::File;
+use crate::schema::file_version;
+
+/// Content a file had before a newer version replaced it.
+#[derive(Queryable, Selectable, Debug, Clone)]
+#[diesel(table_name = file_version)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct FileVersion {
+    pub id: Option<i32>,
+    pub file_id: i32,
+    pub version: i32,
+    pub content_hash: String,
+    pub content_type: String,
+    pub size: i64,
+    pub storage_path: String,
+    pub replaced_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = file_version)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct FileVersionToInsert {
+    pub file_id: i32,
+    pub version: i32,
+    pub content_hash: String,
+    pub content_type: String,
+    pub size: i64,
+    pub storage_path: String,
+}
+
+impl From<&File> for FileVersionToInsert {
+    /// Archives the current content of `current`.
+    fn from(current: &File) -> Self {
+        FileVersionToInsert {
+            file_id: current.id.unwrap_or_default(),
+            version: current.version,
+            content_hash: current.content_hash.clone(),
+            content_type: current.content_type.clone(),
+            size: current.size,
+            storage_path: current.storage_path.clone(),
+        }
+    }
+}
+
+/// How long replaced versions are kept. Either rule alone is enough to prune a version.
+#[derive(Debug, Clone, Copy)]
+pub struct VersionRetention {
+    /// Replaced versions kept per file, newest first. `None` keeps any number.
+    pub keep_last: Option<i64>,
+    /// Versions replaced before this are pruned. `None` keeps them regardless of age.
+    pub replaced_before: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FileVersionResponse {
+    pub version: i32,
+    pub content_hash: String,
+    pub content_type: String,
+    pub size: i64,
+    pub is_current: bool,
+    /// When a newer version took over, absent for the current one.
+    pub replaced_at: Option<NaiveDateTime>,
+}
+
+impl From<FileVersion> for FileVersionResponse {
+    fn from(archived: FileVersion) -> Self {
+        FileVersionResponse {
+            version: archived.version,
+            content_hash: archived.content_hash,
+            content_type: archived.content_type,
+            size: archived.size,
+            is_current: false,
+            replaced_at: archived.replaced_at,
+        }
+    }
+}
+
+impl From<&File> for FileVersionResponse {
+    fn from(current: &File) -> Self {
+        FileVersionResponse {
+            version: current.version,
+            content_hash: current.content_hash.clone(),
+            content_type: current.content_type.clone(),
+            size: current.size,
+            is_current: true,
+            replaced_at: None,
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use chrono::Utc;
+use diesel::{BoolExpressionMethods, ExpressionMethods, NullableExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper};
+use tokio::task;
+use crate::model::apikeymodel::{ApiKey, ApiKeyToInsert};
+use crate::model::usermodel::ConversionError;
+use crate::repository::userrepository::establish_connection;
+use crate::schema::api_key::dsl::*;
+
+/// `last_used_at` is only written when older than this, so busy scripts do not cause a write per request.
+const LAST_USED_RESOLUTION_SECONDS: i64 = 60;
+
+pub async fn insert_api_key(new_key: ApiKeyToInsert) -> Result<ApiKey, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        diesel::insert_into(api_key)
+            .values(new_key)
+            .returning(ApiKey::as_select())
+            .get_result::<ApiKey>(connection)
+    }).await?;
+
+    match res {
+        Ok(created) => Ok(created),
+        Err(diesel_error) => {
+            println!("Database Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error creating API Key".to_string()))
+        }
+    }
+}
+
+pub async fn get_api_key_by_hash(other_key_hash: String) -> Result<Option<ApiKey>, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        api_key
+            .filter(key_hash.eq(other_key_hash))
+            .select(ApiKey::as_select())
+            .first::<ApiKey>(connection)
+            .optional()
+    }).await?;
+
+    match res {
+        Ok(found) => Ok(found),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error loading API Key".to_string()))
+        }
+    }
+}
+
+/// Keys of the user that are not revoked, newest first. Expired keys stay listed so they can be cleaned up.
+pub async fn list_api_keys_for_user(other_user_id: i32) -> Result<Vec<ApiKey>, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        api_key
+            .filter(user_id.eq(other_user_id))
+            .filter(revoked_at.is_null())
+            .order_by(id.desc())
+            .select(ApiKey::as_select())
+            .load::<ApiKey>(connection)
+    }).await?;
+
+    match res {
+        Ok(keys) => Ok(keys),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error listing API Keys".to_string()))
+        }
+    }
+}
+
+pub async fn touch_api_key(key_id: i32) -> Result<(), ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        let used = Utc::now().naive_utc();
+        let stale = used - chrono::Duration::seconds(LAST_USED_RESOLUTION_SECONDS);
+        diesel::update(api_key
+            .filter(id.eq(key_id))
+            .filter(last_used_at.is_null().or(last_used_at.lt(stale))))
+            .set(last_used_at.eq(used))
+            .execute(connection)
+    }).await?;
+
+    match res {
+        Ok(_) => Ok(()),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error updating API Key".to_string()))
+        }
+    }
+}
+
+/// Revokes the key if it belongs to `other_user_id` and is not revoked yet. Returns false otherwise.
+pub async fn revoke_api_key(key_id: i32, other_user_id: i32) -> Result<bool, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        diesel::update(api_key
+            .filter(id.eq(key_id))
+            .filter(user_id.eq(other_user_id))
+            .filter(revoked_at.is_null()))
+            .set(revoked_at.eq(diesel::dsl::now.nullable()))
+            .execute(connection)
+    }).await?;
+
+    match res {
+        Ok(updated) => Ok(updated == 1),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error revoking API Key".to_string()))
+        }
+    }
+}
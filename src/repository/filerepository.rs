@@ -1,15 +1,26 @@
 use crate::model::usermodel::ConversionError;
-use diesel::ExpressionMethods;
-use std::fmt::Error;
-use diesel::{QueryDsl, RunQueryDsl, SelectableHelper};
+use diesel::{BoolExpressionMethods, Connection, ExpressionMethods, NullableExpressionMethods};
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use diesel::{OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper};
 use tokio::task;
+use crate::model::filemodel::{Blob, BlobToInsert, FileListFilter, FileSort, SortOrder};
 use crate::model::usermodel::{File, FileToInsert};
 use crate::model::usermodel::ConversionError::*;
 use crate::repository::userrepository::establish_connection;
+use crate::schema::blob;
 use crate::schema::file::dsl::file;
-use crate::schema::file::{file_name, hashed_file_name};
 
-pub async fn write_name_to_db(storing_file: FileToInsert) -> Result<File,Error> {
+/// Message of the `Conflict` returned when the generated `hashed_file_name` is taken, the caller retries with a new one.
+pub const FILE_TOKEN_TAKEN: &str = "Share token already taken";
+
+fn file_name_taken(other_file_name: &str) -> ConversionError {
+    Conflict(format!("A file named {} already exists in this folder", other_file_name))
+}
+
+/// Inserts the file row. A clash on the UNIQUE `hashed_file_name` comes back as `Conflict(FILE_TOKEN_TAKEN)`,
+/// a live file of the same name in the folder as any other `Conflict`.
+pub async fn write_name_to_db(storing_file: FileToInsert) -> Result<File,ConversionError> {
+    let other_file_name = storing_file.file_name.clone();
     let res = task::spawn_blocking(move || {
         let connection =  &mut establish_connection();
         diesel::insert_into(file)
@@ -23,75 +34,412 @@ pub async fn write_name_to_db(storing_file: FileToInsert) -> Result<File,Error>
 
     match res {
         Ok(Ok(other_file)) => {
-            println!("{:?}", other_file);
+            println!("Stored File {:?}", other_file.id);
             Ok(other_file)
         }
+        Ok(Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, info))) => {
+            println!("Unique Violation: {}", info.message());
+            if info.message().contains("hashed_file_name") {
+                Err(Conflict(FILE_TOKEN_TAKEN.to_string()))
+            } else {
+                Err(file_name_taken(&other_file_name))
+            }
+        }
         Ok(Err(_diesel_error)) => {
             println!("Database Error");
-            Err(Error)
+            Err(ConversionError("Database Error".to_string()))
         }
         Err(_join_error) => { // Outer Err for a tokio::task::JoinError
             println!("Error with Thread");
-            Err(Error)
+            Err(ConversionError("Join Error".to_string()))
         }
     }
 }
 
-pub async fn get_file_name_from_db(other_file_name: String) -> Result<Vec<File>, Error> {
+/// Registers a freshly stored object. If a blob with the same digest already exists its reference count
+/// is bumped instead and the existing blob is returned, so the caller can drop its duplicate object.
+pub async fn store_blob(new_blob: BlobToInsert) -> Result<Blob, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let mut conn = establish_connection();
+
+        diesel::insert_into(blob::table)
+            .values(&new_blob)
+            .on_conflict((blob::hash_algorithm, blob::content_hash))
+            .do_update()
+            .set(blob::ref_count.eq(blob::ref_count + 1))
+            .returning(Blob::as_select())
+            .get_result::<Blob>(&mut conn)
+    }).await?;
 
+    match res {
+        Ok(stored_blob) => Ok(stored_blob),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError("Error storing Blob".to_string()))
+        }
+    }
+}
+
+pub async fn get_blob_by_path(path: String) -> Result<Option<Blob>, ConversionError> {
     let res = task::spawn_blocking(move || {
-        let mut  conn = establish_connection();
+        let mut conn = establish_connection();
 
-        let file_from_db = file.filter(hashed_file_name.eq(other_file_name)).limit(1).load::<File>(&mut conn);
+        blob::table
+            .filter(blob::storage_path.eq(path))
+            .select(Blob::as_select())
+            .first::<Blob>(&mut conn)
+            .optional()
+    }).await?;
 
-        file_from_db
-    }).await;
+    match res {
+        Ok(stored_blob) => Ok(stored_blob),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError("Error loading Blob".to_string()))
+        }
+    }
+}
+
+pub async fn get_file_by_id(other_id: i32) -> Result<Option<File>, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let mut conn = establish_connection();
+
+        file.filter(crate::schema::file::id.eq(other_id))
+            .select(File::as_select())
+            .first::<File>(&mut conn)
+            .optional()
+    }).await?;
 
     match res {
-        Ok(Ok(files)) => {
-            println!("DB Coonection");
-            for filess in files.iter(){
-                println!("CHECKING IF FILE IS LOADING{:?}", filess);
-            }
-            Ok(files)
+        Ok(other_file) => Ok(other_file),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError("Error loading File".to_string()))
         }
-        Ok(Err(_diesel_error)) => {
-            println!("Diesel ORM Error");
-            Err(Error)
+    }
+}
+
+/// One page of the owner's files that are not deleted, ordered by the requested column and the id.
+/// Paging is keyset based: only rows after `filter.after` in that order are returned.
+pub async fn list_files_for_owner(filter: FileListFilter) -> Result<Vec<File>, ConversionError> {
+    use crate::schema::file::dsl as files;
+
+    let res = task::spawn_blocking(move || {
+        let mut conn = establish_connection();
+
+        let mut query = files::file
+            .filter(files::owner_id.eq(filter.owner_id))
+            .filter(files::is_deleted.is_null().or(files::is_deleted.ne(1)))
+            .select(File::as_select())
+            .into_boxed();
+
+        if let Some(other_content_type) = filter.content_type {
+            query = query.filter(files::content_type.eq(other_content_type));
+        }
+        if let Some(min_size) = filter.min_size {
+            query = query.filter(files::size.ge(min_size));
+        }
+        if let Some(max_size) = filter.max_size {
+            query = query.filter(files::size.le(max_size));
+        }
+        if let Some(created_after) = filter.created_after {
+            query = query.filter(files::created_at.ge(created_after));
+        }
+        if let Some(created_before) = filter.created_before {
+            query = query.filter(files::created_at.lt(created_before));
+        }
+        match filter.is_public {
+            Some(true) => query = query.filter(files::is_public.eq(1)),
+            Some(false) => query = query.filter(files::is_public.is_null().or(files::is_public.ne(1))),
+            None => {}
         }
-        Err(_join_error) => {
-            println!("Join Error while Quering DB");
-            Err(Error)
+
+        if let Some(after) = filter.after {
+            let after_id = after.id;
+            let after_name = after.name.unwrap_or_default();
+            let after_size = after.size.unwrap_or_default();
+            let after_created_at = after.created_at.unwrap_or_default();
+            query = match (filter.sort, filter.order) {
+                (FileSort::Name, SortOrder::Asc) => query.filter(files::file_name.gt(after_name.clone())
+                    .or(files::file_name.eq(after_name).and(files::id.gt(after_id)))),
+                (FileSort::Name, SortOrder::Desc) => query.filter(files::file_name.lt(after_name.clone())
+                    .or(files::file_name.eq(after_name).and(files::id.lt(after_id)))),
+                (FileSort::Size, SortOrder::Asc) => query.filter(files::size.gt(after_size)
+                    .or(files::size.eq(after_size).and(files::id.gt(after_id)))),
+                (FileSort::Size, SortOrder::Desc) => query.filter(files::size.lt(after_size)
+                    .or(files::size.eq(after_size).and(files::id.lt(after_id)))),
+                (FileSort::CreatedAt, SortOrder::Asc) => query.filter(files::created_at.gt(after_created_at)
+                    .or(files::created_at.eq(after_created_at).and(files::id.gt(after_id)))),
+                (FileSort::CreatedAt, SortOrder::Desc) => query.filter(files::created_at.lt(after_created_at)
+                    .or(files::created_at.eq(after_created_at).and(files::id.lt(after_id)))),
+            };
+        }
+
+        query = match (filter.sort, filter.order) {
+            (FileSort::Name, SortOrder::Asc) => query.order_by((files::file_name.asc(), files::id.asc())),
+            (FileSort::Name, SortOrder::Desc) => query.order_by((files::file_name.desc(), files::id.desc())),
+            (FileSort::Size, SortOrder::Asc) => query.order_by((files::size.asc(), files::id.asc())),
+            (FileSort::Size, SortOrder::Desc) => query.order_by((files::size.desc(), files::id.desc())),
+            (FileSort::CreatedAt, SortOrder::Asc) => query.order_by((files::created_at.asc(), files::id.asc())),
+            (FileSort::CreatedAt, SortOrder::Desc) => query.order_by((files::created_at.desc(), files::id.desc())),
+        };
+
+        query.limit(filter.limit).load::<File>(&mut conn)
+    }).await?;
+
+    match res {
+        Ok(files) => Ok(files),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError("Error listing Files".to_string()))
         }
     }
 }
 
-pub async fn check_if_file_name_exists(name: String) -> Result<bool,ConversionError>{
+/// Files of `owner` in `folder` that are not deleted, or those at the top level when `folder` is `None`, by name.
+pub async fn list_files_in_folder(owner: i32, folder: Option<i32>) -> Result<Vec<File>, ConversionError> {
+    use crate::schema::file::dsl as files;
+
     let res = task::spawn_blocking(move || {
-        let mut conn =  establish_connection();
-       
-        let count = file.count().filter(file_name.eq(name)).get_result::<i64>(&mut conn);
-        
-        count
-    }).await;
+        let mut conn = establish_connection();
+
+        let mut query = files::file
+            .filter(files::owner_id.eq(owner))
+            .filter(files::is_deleted.is_null().or(files::is_deleted.ne(1)))
+            .select(File::as_select())
+            .into_boxed();
+        query = match folder {
+            Some(folder) => query.filter(files::folder_id.eq(folder)),
+            None => query.filter(files::folder_id.is_null()),
+        };
+        query.order_by((files::file_name.asc(), files::id.asc())).load::<File>(&mut conn)
+    }).await?;
+
     match res {
-        Ok(Ok(count)) => {
-            if count < 1 {
-                Ok(true)
-            }
-            else {
-                Err(ConversionError("Diesel Error".to_string()))
+        Ok(files) => Ok(files),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError("Error listing Files".to_string()))
+        }
+    }
+}
+
+/// The live file of `owner` named `other_file_name` in `folder`, not counting `except`.
+pub async fn find_sibling_file(owner: i32, folder: Option<i32>, other_file_name: String, except: Option<i32>) -> Result<Option<File>, ConversionError> {
+    use crate::schema::file::dsl as files;
+
+    let res = task::spawn_blocking(move || {
+        let mut conn = establish_connection();
+
+        let mut query = files::file
+            .filter(files::owner_id.eq(owner))
+            .filter(files::file_name.eq(other_file_name))
+            .filter(files::is_deleted.is_null().or(files::is_deleted.ne(1)))
+            .select(File::as_select())
+            .into_boxed();
+        query = match folder {
+            Some(folder) => query.filter(files::folder_id.eq(folder)),
+            None => query.filter(files::folder_id.is_null()),
+        };
+        if let Some(except) = except {
+            query = query.filter(files::id.ne(except));
+        }
+        query.first::<File>(&mut conn).optional()
+    }).await?;
+
+    match res {
+        Ok(sibling) => Ok(sibling),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError("Error checking File names".to_string()))
+        }
+    }
+}
+
+/// Changes name and folder of a file of `owner` that is not deleted, `updated_at` follows through the trigger.
+/// Only the row changes, the stored object stays where it is. Returns `None` when no such file exists.
+pub async fn update_file_metadata(other_id: i32, owner: i32, new_file_name: String, new_folder_id: Option<i32>) -> Result<Option<File>, ConversionError> {
+    use crate::schema::file::dsl as files;
+
+    let other_file_name = new_file_name.clone();
+    let res = task::spawn_blocking(move || {
+        let mut conn = establish_connection();
+
+        diesel::update(files::file
+            .filter(files::id.eq(other_id))
+            .filter(files::owner_id.eq(owner))
+            .filter(files::is_deleted.is_null().or(files::is_deleted.ne(1))))
+            .set((files::file_name.eq(new_file_name), files::folder_id.eq(new_folder_id)))
+            .execute(&mut conn)?;
+        // Read back afterwards, RETURNING would not see what the trigger wrote.
+        files::file
+            .filter(files::id.eq(other_id))
+            .filter(files::owner_id.eq(owner))
+            .filter(files::is_deleted.is_null().or(files::is_deleted.ne(1)))
+            .select(File::as_select())
+            .first::<File>(&mut conn)
+            .optional()
+    }).await?;
+
+    match res {
+        Ok(updated) => Ok(updated),
+        Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => Err(file_name_taken(&other_file_name)),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError("Error updating File".to_string()))
+        }
+    }
+}
+
+/// Flips `is_deleted` for a file of `owner`. Returns false when the file does not exist, belongs to someone
+/// else or already is in the requested state, so a second delete keeps the first `deleted_at`. Restoring next
+/// to a live file of the same name is a `Conflict`.
+pub async fn set_file_deleted(other_id: i32, owner: i32, deleted: bool) -> Result<bool, ConversionError> {
+    use crate::schema::file::dsl as files;
+
+    let res = task::spawn_blocking(move || {
+        let mut conn = establish_connection();
+
+        let target = files::file
+            .filter(files::id.eq(other_id))
+            .filter(files::owner_id.eq(owner));
+        if deleted {
+            diesel::update(target.filter(files::is_deleted.is_null().or(files::is_deleted.ne(1))))
+                .set((files::is_deleted.eq(1), files::deleted_at.eq(diesel::dsl::now.nullable())))
+                .execute(&mut conn)
+        } else {
+            diesel::update(target.filter(files::is_deleted.eq(1)))
+                .set((files::is_deleted.eq(0), files::deleted_at.eq(None::<chrono::NaiveDateTime>)))
+                .execute(&mut conn)
+        }
+    }).await?;
+
+    match res {
+        Ok(updated) => Ok(updated == 1),
+        Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+            Err(Conflict("A file with this name already exists in its folder, rename that one first".to_string()))
+        }
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError("Error updating File".to_string()))
+        }
+    }
+}
+
+/// Soft-deleted files of `owner`, most recently deleted first.
+pub async fn list_deleted_files(owner: i32) -> Result<Vec<File>, ConversionError> {
+    use crate::schema::file::dsl as files;
+
+    let res = task::spawn_blocking(move || {
+        let mut conn = establish_connection();
+
+        files::file
+            .filter(files::owner_id.eq(owner))
+            .filter(files::is_deleted.eq(1))
+            .order_by((files::deleted_at.desc(), files::id.desc()))
+            .select(File::as_select())
+            .load::<File>(&mut conn)
+    }).await?;
+
+    match res {
+        Ok(files) => Ok(files),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError("Error listing Files".to_string()))
+        }
+    }
+}
+
+/// Removes a soft-deleted file together with its share links and versions and drops their blob references, all
+/// in one transaction. Returns the storage paths nothing references anymore, the caller deletes the objects.
+pub async fn purge_file(other_id: i32) -> Result<Vec<String>, ConversionError> {
+    use crate::schema::file::dsl as files;
+    use crate::schema::{file_to_link, file_version};
+
+    let res = task::spawn_blocking(move || {
+        let mut conn = establish_connection();
+
+        conn.transaction::<_, DieselError, _>(|conn| {
+            let purged = files::file
+                .filter(files::id.eq(other_id))
+                .filter(files::is_deleted.eq(1))
+                .select(File::as_select())
+                .first::<File>(conn)
+                .optional()?;
+            let Some(purged) = purged else {
+                return Ok(Vec::new())
+            };
+
+            let version_paths = file_version::table
+                .filter(file_version::file_id.eq(other_id))
+                .select(file_version::storage_path)
+                .load::<String>(conn)?;
+            diesel::delete(file_to_link::table.filter(file_to_link::file_id.eq(other_id))).execute(conn)?;
+            diesel::delete(file_version::table.filter(file_version::file_id.eq(other_id))).execute(conn)?;
+            diesel::delete(files::file.filter(files::id.eq(other_id))).execute(conn)?;
+
+            let mut unreferenced = Vec::new();
+            for path in std::iter::once(purged.storage_path).chain(version_paths) {
+                if release_blob_reference(conn, &path)? && !unreferenced.contains(&path) {
+                    unreferenced.push(path);
+                }
             }
+            Ok(unreferenced)
+        })
+    }).await?;
+
+    match res {
+        Ok(paths) => Ok(paths),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError("Error purging File".to_string()))
         }
-        Ok(Err(_diesel_error)) => {
-            println!("Diesel ORM Error");
-            Err(ConversionError("Diesel Error".to_string()))
+    }
+}
+
+/// Drops one reference to the blob at `path` and deletes the blob row once none are left. Returns whether
+/// the stored object is unreferenced now, deleting it from storage is up to the caller.
+pub fn release_blob_reference(conn: &mut diesel::SqliteConnection, path: &str) -> Result<bool, DieselError> {
+    use crate::schema::file::dsl as files;
+    use crate::schema::file_version;
+
+    let remaining = diesel::update(blob::table.filter(blob::storage_path.eq(path)))
+        .set(blob::ref_count.eq(blob::ref_count - 1))
+        .returning(blob::ref_count)
+        .get_result::<i32>(conn)
+        .optional()?;
+    match remaining {
+        Some(count) if count <= 0 => {
+            diesel::delete(blob::table.filter(blob::storage_path.eq(path))).execute(conn)?;
+            Ok(true)
         }
-        Err(_join_error) => {
-            println!("Join Error while Quering DB");
-            Err(ConversionError("Join Error".to_string()))
+        Some(_) => Ok(false),
+        // Objects without a blob row are only safe to remove once no file or version points at them.
+        None => {
+            let files_left = files::file.count().filter(files::storage_path.eq(path)).get_result::<i64>(conn)?;
+            let versions_left = file_version::table.count().filter(file_version::storage_path.eq(path)).get_result::<i64>(conn)?;
+            Ok(files_left + versions_left == 0)
         }
-        
     }
 }
 
+/// Gives back the reference of a blob that ended up unused, e.g. when registering its file failed.
+/// Returns the storage path once nothing references the object anymore, the caller deletes it.
+pub async fn release_blob(path: String) -> Result<Option<String>, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let mut conn = establish_connection();
+
+        conn.transaction::<_, DieselError, _>(|conn| {
+            let unreferenced = release_blob_reference(conn, &path)?;
+            Ok(unreferenced.then_some(path))
+        })
+    }).await?;
+
+    match res {
+        Ok(path) => Ok(path),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError("Error releasing Blob".to_string()))
+        }
+    }
+}
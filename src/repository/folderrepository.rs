@@ -0,0 +1,189 @@
+use diesel::{BoolExpressionMethods, Connection, ExpressionMethods, NullableExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper};
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use tokio::task;
+use crate::model::foldermodel::{folder_path, Folder, FolderToInsert, FolderUpdate};
+use crate::model::usermodel::ConversionError;
+use crate::model::usermodel::ConversionError::*;
+use crate::repository::userrepository::establish_connection;
+use crate::schema::{file, folder};
+
+fn sibling_conflict(diesel_error: DieselError, action: &str) -> ConversionError {
+    match diesel_error {
+        DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+            Conflict("A folder with this name already exists there".to_string())
+        }
+        diesel_error => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            ConversionError(format!("Error {} Folder", action))
+        }
+    }
+}
+
+/// Folders below `root` of the same owner, found through the path cache. Compared in Rust, SQLite's
+/// `LIKE` ignores case and would mix up `/Docs` with `/docs`.
+fn load_descendants(conn: &mut diesel::SqliteConnection, root: &Folder) -> Result<Vec<Folder>, DieselError> {
+    let prefix = format!("{}/", root.path);
+    let owned = folder::table
+        .filter(folder::owner_id.eq(root.owner_id))
+        .select(Folder::as_select())
+        .load::<Folder>(conn)?;
+    Ok(owned.into_iter().filter(|other_folder| other_folder.path.starts_with(&prefix)).collect())
+}
+
+/// Inserts the folder. A sibling with the same name comes back as `Conflict`.
+pub async fn insert_folder(new_folder: FolderToInsert) -> Result<Folder, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let mut conn = establish_connection();
+        diesel::insert_into(folder::table)
+            .values(new_folder)
+            .returning(Folder::as_select())
+            .get_result::<Folder>(&mut conn)
+    }).await?;
+
+    res.map_err(|diesel_error| sibling_conflict(diesel_error, "creating"))
+}
+
+pub async fn get_folder_by_id(other_id: i32) -> Result<Option<Folder>, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let mut conn = establish_connection();
+        folder::table
+            .filter(folder::id.eq(other_id))
+            .select(Folder::as_select())
+            .first::<Folder>(&mut conn)
+            .optional()
+    }).await?;
+
+    match res {
+        Ok(found) => Ok(found),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError("Error loading Folder".to_string()))
+        }
+    }
+}
+
+/// Direct subfolders of `parent`, or the top level folders of `owner` when `parent` is `None`, by name.
+pub async fn list_child_folders(owner: i32, parent: Option<i32>) -> Result<Vec<Folder>, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let mut conn = establish_connection();
+        let mut query = folder::table
+            .filter(folder::owner_id.eq(owner))
+            .select(Folder::as_select())
+            .into_boxed();
+        query = match parent {
+            Some(parent) => query.filter(folder::parent_id.eq(parent)),
+            None => query.filter(folder::parent_id.is_null()),
+        };
+        query.order_by((folder::name.asc(), folder::id.asc())).load::<Folder>(&mut conn)
+    }).await?;
+
+    match res {
+        Ok(folders) => Ok(folders),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError("Error listing Folders".to_string()))
+        }
+    }
+}
+
+/// Renames and/or moves a folder and rewrites the cached paths of everything below it in one transaction.
+/// The new parent is read in the same transaction, so a concurrent move can not sneak a cycle past the check.
+pub async fn update_folder(other_id: i32, new_name: String, new_parent_id: Option<i32>) -> Result<FolderUpdate, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let mut conn = establish_connection();
+
+        conn.immediate_transaction::<_, DieselError, _>(|conn| {
+            let load = |conn: &mut diesel::SqliteConnection, id: i32| folder::table
+                .filter(folder::id.eq(id))
+                .select(Folder::as_select())
+                .first::<Folder>(conn)
+                .optional();
+            let Some(existing) = load(conn, other_id)? else {
+                return Ok(FolderUpdate::FolderMissing)
+            };
+            let parent = match new_parent_id {
+                Some(parent_id) => match load(conn, parent_id)? {
+                    Some(parent) => Some(parent),
+                    None => return Ok(FolderUpdate::ParentMissing),
+                },
+                None => None,
+            };
+            if let Some(parent) = &parent
+                && (parent.id == existing.id || parent.path.starts_with(&format!("{}/", existing.path))) {
+                return Ok(FolderUpdate::IntoItself)
+            }
+            let new_path = folder_path(parent.as_ref(), &new_name);
+
+            for descendant in load_descendants(conn, &existing)? {
+                let moved_path = format!("{}{}", new_path, &descendant.path[existing.path.len()..]);
+                diesel::update(folder::table.filter(folder::id.eq(descendant.id)))
+                    .set(folder::path.eq(moved_path))
+                    .execute(conn)?;
+            }
+
+            diesel::update(folder::table.filter(folder::id.eq(other_id)))
+                .set((
+                    folder::name.eq(new_name),
+                    folder::parent_id.eq(new_parent_id),
+                    folder::path.eq(new_path),
+                    folder::updated_at.eq(diesel::dsl::now.nullable()),
+                ))
+                .returning(Folder::as_select())
+                .get_result::<Folder>(conn)
+                .map(FolderUpdate::Updated)
+        })
+    }).await?;
+
+    res.map_err(|diesel_error| sibling_conflict(diesel_error, "updating"))
+}
+
+/// Deletes the folder with all subfolders. Files in them that are not trashed yet go to the trash, trashed
+/// ones are restored to the top level later. Without `recursive` only an empty folder is deleted.
+/// Returns `None` when the folder does not exist and `Some(false)` when it was not empty.
+pub async fn delete_folder_tree(other_id: i32, recursive: bool) -> Result<Option<bool>, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let mut conn = establish_connection();
+
+        conn.transaction::<_, DieselError, _>(|conn| {
+            let Some(existing) = folder::table
+                .filter(folder::id.eq(other_id))
+                .select(Folder::as_select())
+                .first::<Folder>(conn)
+                .optional()? else {
+                return Ok(None)
+            };
+
+            let mut folder_ids: Vec<i32> = load_descendants(conn, &existing)?
+                .into_iter()
+                .filter_map(|descendant| descendant.id)
+                .collect();
+            let live_files = file::table
+                .filter(file::folder_id.eq(other_id))
+                .filter(file::is_deleted.is_null().or(file::is_deleted.ne(1)))
+                .count()
+                .get_result::<i64>(conn)?;
+            if !recursive && (!folder_ids.is_empty() || live_files > 0) {
+                return Ok(Some(false))
+            }
+            folder_ids.push(other_id);
+
+            let contained = || file::table.filter(file::folder_id.eq_any(folder_ids.clone()));
+            diesel::update(contained().filter(file::is_deleted.is_null().or(file::is_deleted.ne(1))))
+                .set((file::is_deleted.eq(1), file::deleted_at.eq(diesel::dsl::now.nullable())))
+                .execute(conn)?;
+            diesel::update(contained())
+                .set(file::folder_id.eq(None::<i32>))
+                .execute(conn)?;
+            diesel::delete(folder::table.filter(folder::id.eq_any(folder_ids))).execute(conn)?;
+            Ok(Some(true))
+        })
+    }).await?;
+
+    match res {
+        Ok(deleted) => Ok(deleted),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError("Error deleting Folder".to_string()))
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use diesel::{BoolExpressionMethods, Connection, ExpressionMethods, NullableExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper};
+use diesel::result::Error as DieselError;
+use tokio::task;
+use crate::model::mfamodel::{RecoveryCodeToInsert, UserMfa, UserMfaToInsert};
+use crate::model::usermodel::ConversionError;
+use crate::repository::userrepository::establish_connection;
+use crate::schema::{mfa_recovery_code, user_mfa};
+
+pub async fn get_user_mfa(other_user_id: i32) -> Result<Option<UserMfa>, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        user_mfa::table
+            .filter(user_mfa::user_id.eq(other_user_id))
+            .select(UserMfa::as_select())
+            .first::<UserMfa>(connection)
+            .optional()
+    }).await?;
+
+    match res {
+        Ok(found) => Ok(found),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error loading MFA settings".to_string()))
+        }
+    }
+}
+
+/// Starts enrollment over with a new secret. A confirmed enrollment is left alone, returns false then.
+pub async fn replace_unconfirmed_user_mfa(new_mfa: UserMfaToInsert) -> Result<bool, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let mut conn = establish_connection();
+        conn.transaction::<_, DieselError, _>(|conn| {
+            let confirmed = user_mfa::table
+                .filter(user_mfa::user_id.eq(new_mfa.user_id))
+                .filter(user_mfa::confirmed_at.is_not_null())
+                .count()
+                .get_result::<i64>(conn)?;
+            if confirmed > 0 {
+                return Ok(false)
+            }
+            diesel::delete(user_mfa::table.filter(user_mfa::user_id.eq(new_mfa.user_id))).execute(conn)?;
+            diesel::insert_into(user_mfa::table).values(&new_mfa).execute(conn)?;
+            Ok(true)
+        })
+    }).await?;
+
+    match res {
+        Ok(replaced) => Ok(replaced),
+        Err(diesel_error) => {
+            println!("Database Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error storing MFA settings".to_string()))
+        }
+    }
+}
+
+/// Confirms enrollment with the step of the first accepted code and stores the recovery codes.
+/// Returns false when the enrollment was confirmed already.
+pub async fn confirm_user_mfa(other_user_id: i32, step: i64, codes: Vec<RecoveryCodeToInsert>) -> Result<bool, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let mut conn = establish_connection();
+        conn.transaction::<_, DieselError, _>(|conn| {
+            let updated = diesel::update(user_mfa::table
+                .filter(user_mfa::user_id.eq(other_user_id))
+                .filter(user_mfa::confirmed_at.is_null()))
+                .set((user_mfa::confirmed_at.eq(diesel::dsl::now.nullable()), user_mfa::last_used_step.eq(step)))
+                .execute(conn)?;
+            if updated != 1 {
+                return Ok(false)
+            }
+            diesel::delete(mfa_recovery_code::table.filter(mfa_recovery_code::user_id.eq(other_user_id))).execute(conn)?;
+            diesel::insert_into(mfa_recovery_code::table).values(&codes).execute(conn)?;
+            Ok(true)
+        })
+    }).await?;
+
+    match res {
+        Ok(confirmed) => Ok(confirmed),
+        Err(diesel_error) => {
+            println!("Database Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error confirming MFA".to_string()))
+        }
+    }
+}
+
+/// Records `step` as used if it is newer than the last accepted one. Returns false for a replayed code.
+pub async fn use_totp_step(other_user_id: i32, step: i64) -> Result<bool, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        diesel::update(user_mfa::table
+            .filter(user_mfa::user_id.eq(other_user_id))
+            .filter(user_mfa::last_used_step.is_null().or(user_mfa::last_used_step.lt(step))))
+            .set(user_mfa::last_used_step.eq(step))
+            .execute(connection)
+    }).await?;
+
+    match res {
+        Ok(updated) => Ok(updated == 1),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error updating MFA settings".to_string()))
+        }
+    }
+}
+
+/// Marks the recovery code as used. Returns false when it is unknown or was used before.
+pub async fn use_recovery_code(other_user_id: i32, other_code_hash: String) -> Result<bool, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        diesel::update(mfa_recovery_code::table
+            .filter(mfa_recovery_code::user_id.eq(other_user_id))
+            .filter(mfa_recovery_code::code_hash.eq(other_code_hash))
+            .filter(mfa_recovery_code::used_at.is_null()))
+            .set(mfa_recovery_code::used_at.eq(diesel::dsl::now.nullable()))
+            .execute(connection)
+    }).await?;
+
+    match res {
+        Ok(updated) => Ok(updated == 1),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error using recovery code".to_string()))
+        }
+    }
+}
+
+/// Replaces all recovery codes of the user, earlier ones stop working.
+pub async fn replace_recovery_codes(other_user_id: i32, codes: Vec<RecoveryCodeToInsert>) -> Result<(), ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let mut conn = establish_connection();
+        conn.transaction::<_, DieselError, _>(|conn| {
+            diesel::delete(mfa_recovery_code::table.filter(mfa_recovery_code::user_id.eq(other_user_id))).execute(conn)?;
+            diesel::insert_into(mfa_recovery_code::table).values(&codes).execute(conn)
+        })
+    }).await?;
+
+    match res {
+        Ok(_) => Ok(()),
+        Err(diesel_error) => {
+            println!("Database Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error storing recovery codes".to_string()))
+        }
+    }
+}
+
+/// Turns MFA off, removing the secret and every recovery code.
+pub async fn delete_user_mfa(other_user_id: i32) -> Result<(), ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let mut conn = establish_connection();
+        conn.transaction::<_, DieselError, _>(|conn| {
+            diesel::delete(mfa_recovery_code::table.filter(mfa_recovery_code::user_id.eq(other_user_id))).execute(conn)?;
+            diesel::delete(user_mfa::table.filter(user_mfa::user_id.eq(other_user_id))).execute(conn)
+        })
+    }).await?;
+
+    match res {
+        Ok(_) => Ok(()),
+        Err(diesel_error) => {
+            println!("Database Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error disabling MFA".to_string()))
+        }
+    }
+}
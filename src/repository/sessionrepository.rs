@@ -0,0 +1,165 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::{ExpressionMethods, NullableExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper};
+use tokio::task;
+use crate::model::sessionmodel::{Session, SessionToInsert};
+use crate::model::usermodel::ConversionError;
+use crate::repository::userrepository::establish_connection;
+use crate::schema::session::dsl::*;
+
+/// `last_seen_at` is only written when older than this, so busy clients do not cause a write per request.
+const LAST_SEEN_RESOLUTION_SECONDS: i64 = 60;
+
+pub async fn create_session(new_session: SessionToInsert) -> Result<Session, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        diesel::insert_into(session)
+            .values(new_session)
+            .returning(Session::as_select())
+            .get_result::<Session>(connection)
+    }).await?;
+
+    match res {
+        Ok(created) => Ok(created),
+        Err(diesel_error) => {
+            println!("Database Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error creating Session".to_string()))
+        }
+    }
+}
+
+pub async fn get_session(session_id: i32) -> Result<Option<Session>, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        session
+            .filter(id.eq(session_id))
+            .select(Session::as_select())
+            .first::<Session>(connection)
+            .optional()
+    }).await?;
+
+    match res {
+        Ok(found) => Ok(found),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error loading Session".to_string()))
+        }
+    }
+}
+
+pub async fn get_session_by_family(other_family_id: String) -> Result<Option<Session>, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        session
+            .filter(family_id.eq(other_family_id))
+            .select(Session::as_select())
+            .first::<Session>(connection)
+            .optional()
+    }).await?;
+
+    match res {
+        Ok(found) => Ok(found),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error loading Session".to_string()))
+        }
+    }
+}
+
+/// Sessions that are neither revoked nor expired, most recently used first.
+pub async fn list_active_sessions(other_user_id: i32) -> Result<Vec<Session>, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        session
+            .filter(user_id.eq(other_user_id))
+            .filter(revoked_at.is_null())
+            .filter(expires_at.gt(diesel::dsl::now))
+            .order_by((last_seen_at.desc(), id.desc()))
+            .select(Session::as_select())
+            .load::<Session>(connection)
+    }).await?;
+
+    match res {
+        Ok(sessions) => Ok(sessions),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error listing Sessions".to_string()))
+        }
+    }
+}
+
+pub async fn touch_session(session_id: i32) -> Result<(), ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        let seen = Utc::now().naive_utc();
+        let stale = seen - chrono::Duration::seconds(LAST_SEEN_RESOLUTION_SECONDS);
+        diesel::update(session.filter(id.eq(session_id)).filter(last_seen_at.lt(stale)))
+            .set(last_seen_at.eq(seen))
+            .execute(connection)
+    }).await?;
+
+    match res {
+        Ok(_) => Ok(()),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error updating Session".to_string()))
+        }
+    }
+}
+
+/// Moves the expiry along with a freshly rotated refresh token.
+pub async fn extend_session(session_id: i32, new_expires_at: NaiveDateTime) -> Result<(), ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        diesel::update(session.filter(id.eq(session_id)))
+            .set((expires_at.eq(new_expires_at), last_seen_at.eq(diesel::dsl::now.nullable())))
+            .execute(connection)
+    }).await?;
+
+    match res {
+        Ok(_) => Ok(()),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error updating Session".to_string()))
+        }
+    }
+}
+
+/// Revokes the session if it belongs to `other_user_id` and still is active. Returns false otherwise.
+pub async fn revoke_session(session_id: i32, other_user_id: i32) -> Result<bool, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        diesel::update(session
+            .filter(id.eq(session_id))
+            .filter(user_id.eq(other_user_id))
+            .filter(revoked_at.is_null()))
+            .set(revoked_at.eq(diesel::dsl::now.nullable()))
+            .execute(connection)
+    }).await?;
+
+    match res {
+        Ok(updated) => Ok(updated == 1),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error revoking Session".to_string()))
+        }
+    }
+}
+
+pub async fn revoke_sessions_for_user(other_user_id: i32) -> Result<usize, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        diesel::update(session
+            .filter(user_id.eq(other_user_id))
+            .filter(revoked_at.is_null()))
+            .set(revoked_at.eq(diesel::dsl::now.nullable()))
+            .execute(connection)
+    }).await?;
+
+    match res {
+        Ok(revoked) => Ok(revoked),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error revoking Sessions".to_string()))
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use chrono::Utc;
+use diesel::{BoolExpressionMethods, ExpressionMethods, NullableExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper};
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use tokio::task;
+use crate::model::sharemodel::{ShareLink, ShareLinkToInsert};
+use crate::model::usermodel::ConversionError;
+use crate::repository::userrepository::establish_connection;
+use crate::schema::file_to_link::dsl::*;
+
+/// Inserts the share link. A clash on the UNIQUE `link` token comes back as `Conflict`.
+pub async fn insert_share_link(new_link: ShareLinkToInsert) -> Result<ShareLink, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        diesel::insert_into(file_to_link)
+            .values(new_link)
+            .returning(ShareLink::as_select())
+            .get_result::<ShareLink>(connection)
+    }).await?;
+
+    match res {
+        Ok(share) => Ok(share),
+        Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, info)) => {
+            println!("Unique Violation: {}", info.message());
+            Err(ConversionError::Conflict("Share token already taken".to_string()))
+        }
+        Err(diesel_error) => {
+            println!("Database Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error creating Share Link".to_string()))
+        }
+    }
+}
+
+pub async fn get_share_link(token: String) -> Result<Option<ShareLink>, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        file_to_link
+            .filter(link.eq(token))
+            .select(ShareLink::as_select())
+            .first::<ShareLink>(connection)
+            .optional()
+    }).await?;
+
+    res.map_err(|diesel_error| {
+        println!("Diesel ORM Error: {}", diesel_error);
+        ConversionError::ConversionError("Error loading Share Link".to_string())
+    })
+}
+
+pub async fn get_share_link_by_id(link_id: i32) -> Result<Option<ShareLink>, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        file_to_link
+            .filter(id.eq(link_id))
+            .select(ShareLink::as_select())
+            .first::<ShareLink>(connection)
+            .optional()
+    }).await?;
+
+    res.map_err(|diesel_error| {
+        println!("Diesel ORM Error: {}", diesel_error);
+        ConversionError::ConversionError("Error loading Share Link".to_string())
+    })
+}
+
+pub async fn list_share_links_for_file(other_file_id: i32) -> Result<Vec<ShareLink>, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        file_to_link
+            .filter(file_id.eq(other_file_id))
+            .order(id.asc())
+            .select(ShareLink::as_select())
+            .load::<ShareLink>(connection)
+    }).await?;
+
+    res.map_err(|diesel_error| {
+        println!("Diesel ORM Error: {}", diesel_error);
+        ConversionError::ConversionError("Error loading Share Links".to_string())
+    })
+}
+
+pub async fn revoke_share_link(link_id: i32) -> Result<bool, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        diesel::update(file_to_link.filter(id.eq(link_id)).filter(revoked_at.is_null()))
+            .set(revoked_at.eq(Utc::now().naive_utc()))
+            .execute(connection)
+    }).await?;
+
+    match res {
+        Ok(updated) => Ok(updated == 1),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error revoking Share Link".to_string()))
+        }
+    }
+}
+
+/// Counts one download if the link still has downloads left. Done in a single UPDATE so
+/// concurrent requests can never go past `max_downloads`.
+pub async fn count_download(link_id: i32) -> Result<bool, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        diesel::update(file_to_link
+            .filter(id.eq(link_id))
+            .filter(max_downloads.is_null().or(download_count.lt(max_downloads.assume_not_null()))))
+            .set(download_count.eq(download_count + 1))
+            .execute(connection)
+    }).await?;
+
+    match res {
+        Ok(updated) => Ok(updated == 1),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error counting Download".to_string()))
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use chrono::NaiveDateTime;
+use diesel::{BoolExpressionMethods, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper, SqliteConnection};
+use diesel::result::Error as DieselError;
+use tokio::task;
+use crate::model::throttlemodel::{LoginLockoutToInsert, LoginThrottle, ReservedThrottle, ThrottleCharge, ThrottleReservation};
+use crate::model::usermodel::ConversionError;
+use crate::repository::userrepository::establish_connection;
+use crate::schema::{login_lockout, login_throttle};
+
+fn find_login_throttle(conn: &mut SqliteConnection, key: &str) -> Result<Option<LoginThrottle>, DieselError> {
+    login_throttle::table
+        .filter(login_throttle::throttle_key.eq(key))
+        .select(LoginThrottle::as_select())
+        .first::<LoginThrottle>(conn)
+        .optional()
+}
+
+/// Counts an attempt as a failure against every charged key before its credentials are checked, in one write
+/// transaction, so parallel guesses can not all pass the check before any of them is counted. When one of the keys
+/// is still blocked nothing is counted. A count last touched before `restart_before`, or one that reached
+/// `restart_from` and whose block ran out, starts over at one.
+pub async fn reserve_throttled_attempt(charges: Vec<ThrottleCharge>, now: NaiveDateTime) -> Result<ThrottleReservation, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+
+        connection.immediate_transaction::<_, DieselError, _>(|conn| {
+            let mut previous_rows = Vec::with_capacity(charges.len());
+            let mut blocked: Option<NaiveDateTime> = None;
+            for charge in &charges {
+                let previous = find_login_throttle(conn, &charge.key)?;
+                if let Some(blocked_until) = previous.as_ref().and_then(|throttle| throttle.blocked_until)
+                    && blocked_until > now {
+                    blocked = Some(blocked.map_or(blocked_until, |other| other.max(blocked_until)));
+                }
+                previous_rows.push(previous);
+            }
+            if let Some(blocked_until) = blocked {
+                return Ok(ThrottleReservation::Blocked(blocked_until))
+            }
+
+            let mut reserved = Vec::with_capacity(charges.len());
+            for (charge, previous) in charges.into_iter().zip(previous_rows) {
+                let key = charge.key;
+                diesel::update(login_throttle::table
+                    .filter(login_throttle::throttle_key.eq(&key))
+                    .filter(login_throttle::last_failure_at.lt(charge.restart_before)
+                        .or(login_throttle::failures.ge(charge.restart_from).and(login_throttle::blocked_until.le(now)))))
+                    .set(login_throttle::failures.eq(0))
+                    .execute(conn)?;
+                let failures = diesel::insert_into(login_throttle::table)
+                    .values((
+                        login_throttle::throttle_key.eq(&key),
+                        login_throttle::failures.eq(1),
+                        login_throttle::last_failure_at.eq(now),
+                    ))
+                    .on_conflict(login_throttle::throttle_key)
+                    .do_update()
+                    .set((
+                        login_throttle::failures.eq(login_throttle::failures + 1),
+                        login_throttle::last_failure_at.eq(now),
+                    ))
+                    .returning(login_throttle::failures)
+                    .get_result::<i32>(conn)?;
+                let blocked_until = (charge.block_after)(failures);
+                diesel::update(login_throttle::table.filter(login_throttle::throttle_key.eq(&key)))
+                    .set(login_throttle::blocked_until.eq(blocked_until))
+                    .execute(conn)?;
+                reserved.push(ReservedThrottle {
+                    previous,
+                    counted: LoginThrottle { throttle_key: key, failures, last_failure_at: now, blocked_until },
+                });
+            }
+            Ok(ThrottleReservation::Reserved(reserved))
+        })
+    }).await?;
+
+    match res {
+        Ok(reservation) => Ok(reservation),
+        Err(diesel_error) => {
+            println!("Database Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error storing login throttle".to_string()))
+        }
+    }
+}
+
+/// Takes a reserved attempt back. A row nobody counted against since is restored as it was, otherwise only the
+/// attempt's own failure is taken off and the newer block stays.
+pub async fn refund_throttled_attempt(reserved: Vec<ReservedThrottle>) -> Result<(), ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+
+        connection.immediate_transaction::<_, DieselError, _>(|conn| {
+            for reservation in reserved {
+                let key = reservation.counted.throttle_key.clone();
+                match find_login_throttle(conn, &key)? {
+                    None => {}
+                    Some(current) if current == reservation.counted => match reservation.previous {
+                        Some(previous) => {
+                            diesel::update(login_throttle::table.filter(login_throttle::throttle_key.eq(&key)))
+                                .set((
+                                    login_throttle::failures.eq(previous.failures),
+                                    login_throttle::last_failure_at.eq(previous.last_failure_at),
+                                    login_throttle::blocked_until.eq(previous.blocked_until),
+                                ))
+                                .execute(conn)?;
+                        }
+                        None => {
+                            diesel::delete(login_throttle::table.filter(login_throttle::throttle_key.eq(&key)))
+                                .execute(conn)?;
+                        }
+                    },
+                    Some(_) => {
+                        diesel::update(login_throttle::table
+                            .filter(login_throttle::throttle_key.eq(&key))
+                            .filter(login_throttle::failures.gt(0)))
+                            .set(login_throttle::failures.eq(login_throttle::failures - 1))
+                            .execute(conn)?;
+                    }
+                }
+            }
+            Ok(())
+        })
+    }).await?;
+
+    match res {
+        Ok(()) => Ok(()),
+        Err(diesel_error) => {
+            println!("Database Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error storing login throttle".to_string()))
+        }
+    }
+}
+
+pub async fn delete_login_throttle(key: String) -> Result<(), ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        diesel::delete(login_throttle::table.filter(login_throttle::throttle_key.eq(key)))
+            .execute(connection)
+    }).await?;
+
+    match res {
+        Ok(_) => Ok(()),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error clearing login throttle".to_string()))
+        }
+    }
+}
+
+pub async fn insert_login_lockout(lockout: LoginLockoutToInsert) -> Result<(), ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        diesel::insert_into(login_lockout::table)
+            .values(lockout)
+            .execute(connection)
+    }).await?;
+
+    match res {
+        Ok(_) => Ok(()),
+        Err(diesel_error) => {
+            println!("Database Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error recording lockout".to_string()))
+        }
+    }
+}
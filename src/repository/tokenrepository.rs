@@ -0,0 +1,146 @@
+use diesel::{ExpressionMethods, NullableExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper};
+use tokio::task;
+use crate::model::tokenmodel::{RefreshToken, RefreshTokenToInsert, RevokedTokenToInsert};
+use crate::model::usermodel::ConversionError;
+use crate::repository::userrepository::establish_connection;
+use crate::schema::refresh_token::dsl::*;
+
+pub async fn insert_refresh_token(new_token: RefreshTokenToInsert) -> Result<RefreshToken, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        diesel::insert_into(refresh_token)
+            .values(new_token)
+            .returning(RefreshToken::as_select())
+            .get_result::<RefreshToken>(connection)
+    }).await?;
+
+    match res {
+        Ok(stored_token) => Ok(stored_token),
+        Err(diesel_error) => {
+            println!("Database Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error storing Refresh Token".to_string()))
+        }
+    }
+}
+
+pub async fn get_refresh_token(other_token_hash: String) -> Result<Option<RefreshToken>, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        refresh_token
+            .filter(token_hash.eq(other_token_hash))
+            .select(RefreshToken::as_select())
+            .first::<RefreshToken>(connection)
+            .optional()
+    }).await?;
+
+    match res {
+        Ok(stored_token) => Ok(stored_token),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error loading Refresh Token".to_string()))
+        }
+    }
+}
+
+/// Marks a token as exchanged. Returns false if it was used or revoked in the meantime, which means
+/// two requests raced with the same token.
+pub async fn use_refresh_token(token_id: i32) -> Result<bool, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        diesel::update(refresh_token
+            .filter(id.eq(token_id))
+            .filter(used_at.is_null())
+            .filter(revoked_at.is_null()))
+            .set(used_at.eq(diesel::dsl::now.nullable()))
+            .execute(connection)
+    }).await?;
+
+    match res {
+        Ok(updated) => Ok(updated == 1),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error updating Refresh Token".to_string()))
+        }
+    }
+}
+
+pub async fn revoke_refresh_token_family(other_family_id: String) -> Result<usize, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        diesel::update(refresh_token
+            .filter(family_id.eq(other_family_id))
+            .filter(revoked_at.is_null()))
+            .set(revoked_at.eq(diesel::dsl::now.nullable()))
+            .execute(connection)
+    }).await?;
+
+    match res {
+        Ok(revoked) => Ok(revoked),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error revoking Refresh Tokens".to_string()))
+        }
+    }
+}
+
+pub async fn revoke_refresh_tokens_for_user(other_user_id: i32) -> Result<usize, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        diesel::update(refresh_token
+            .filter(user_id.eq(other_user_id))
+            .filter(revoked_at.is_null()))
+            .set(revoked_at.eq(diesel::dsl::now.nullable()))
+            .execute(connection)
+    }).await?;
+
+    match res {
+        Ok(revoked) => Ok(revoked),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error revoking Refresh Tokens".to_string()))
+        }
+    }
+}
+
+/// Adds an access token to the revocation list, revoking it twice is a no-op.
+pub async fn insert_revoked_token(revoked: RevokedTokenToInsert) -> Result<(), ConversionError> {
+    use crate::schema::revoked_token;
+
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        diesel::insert_into(revoked_token::table)
+            .values(revoked)
+            .on_conflict(revoked_token::jti)
+            .do_nothing()
+            .execute(connection)
+    }).await?;
+
+    match res {
+        Ok(_) => Ok(()),
+        Err(diesel_error) => {
+            println!("Database Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error revoking Token".to_string()))
+        }
+    }
+}
+
+/// Drops entries of tokens that expired anyway and returns the ids of the ones still revoked.
+pub async fn load_revoked_token_ids() -> Result<Vec<String>, ConversionError> {
+    use crate::schema::revoked_token;
+
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        diesel::delete(revoked_token::table.filter(revoked_token::expires_at.le(diesel::dsl::now))).execute(connection)?;
+        revoked_token::table
+            .select(revoked_token::jti)
+            .load::<String>(connection)
+    }).await?;
+
+    match res {
+        Ok(revoked) => Ok(revoked),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error loading revoked Tokens".to_string()))
+        }
+    }
+}
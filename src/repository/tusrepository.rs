@@ -0,0 +1,131 @@
+use chrono::NaiveDateTime;
+use diesel::{Connection, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper};
+use diesel::result::Error as DieselError;
+use tokio::task;
+use crate::model::tusmodel::{TusUpload, TusUploadPart, TusUploadPartToInsert, TusUploadToInsert};
+use crate::model::usermodel::ConversionError;
+use crate::repository::userrepository::establish_connection;
+use crate::schema::tus_upload::dsl::*;
+use crate::schema::tus_upload_part;
+
+pub async fn create_tus_upload(new_upload: TusUploadToInsert) -> Result<TusUpload, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        diesel::insert_into(tus_upload)
+            .values(new_upload)
+            .returning(TusUpload::as_select())
+            .get_result::<TusUpload>(connection)
+    }).await?;
+
+    match res {
+        Ok(upload) => Ok(upload),
+        Err(diesel_error) => {
+            println!("Database Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error creating Upload".to_string()))
+        }
+    }
+}
+
+pub async fn get_tus_upload(other_upload_id: String) -> Result<Option<TusUpload>, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        tus_upload
+            .filter(upload_id.eq(other_upload_id))
+            .select(TusUpload::as_select())
+            .first::<TusUpload>(connection)
+            .optional()
+    }).await?;
+
+    match res {
+        Ok(upload) => Ok(upload),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error loading Upload".to_string()))
+        }
+    }
+}
+
+/// Moves the offset past `part` and records it, only if nobody else moved the offset in the meantime.
+/// Returns false on a lost race, the part is then not recorded.
+pub async fn advance_tus_offset(part: TusUploadPartToInsert) -> Result<bool, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        connection.transaction::<_, DieselError, _>(|conn| {
+            let updated = diesel::update(tus_upload.filter(upload_id.eq(&part.upload_id)).filter(upload_offset.eq(part.part_offset)))
+                .set((upload_offset.eq(part.part_offset + part.size), updated_at.eq(diesel::dsl::now)))
+                .execute(conn)?;
+            if updated != 1 {
+                return Ok(false)
+            }
+            diesel::insert_into(tus_upload_part::table)
+                .values(&part)
+                .execute(conn)?;
+            Ok(true)
+        })
+    }).await?;
+
+    match res {
+        Ok(advanced) => Ok(advanced),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error updating Upload".to_string()))
+        }
+    }
+}
+
+/// The recorded parts of an upload in upload order.
+pub async fn list_tus_upload_parts(other_upload_id: String) -> Result<Vec<TusUploadPart>, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        tus_upload_part::table
+            .filter(tus_upload_part::upload_id.eq(other_upload_id))
+            .order(tus_upload_part::part_offset.asc())
+            .select(TusUploadPart::as_select())
+            .load::<TusUploadPart>(connection)
+    }).await?;
+
+    match res {
+        Ok(parts) => Ok(parts),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error loading Upload parts".to_string()))
+        }
+    }
+}
+
+/// Ids of uploads that did not move since `before`.
+pub async fn list_stale_tus_uploads(before: NaiveDateTime) -> Result<Vec<String>, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        tus_upload
+            .filter(updated_at.lt(before))
+            .select(upload_id)
+            .load::<String>(connection)
+    }).await?;
+
+    match res {
+        Ok(ids) => Ok(ids),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error loading Uploads".to_string()))
+        }
+    }
+}
+
+pub async fn delete_tus_upload(other_upload_id: String) -> Result<(), ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+        connection.transaction::<_, DieselError, _>(|conn| {
+            diesel::delete(tus_upload_part::table.filter(tus_upload_part::upload_id.eq(&other_upload_id))).execute(conn)?;
+            diesel::delete(tus_upload.filter(upload_id.eq(&other_upload_id))).execute(conn)
+        })
+    }).await?;
+
+    match res {
+        Ok(_) => Ok(()),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error deleting Upload".to_string()))
+        }
+    }
+}
@@ -1,40 +1,44 @@
 use std::env;
 use std::fmt::Error;
-use diesel::{Connection, ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl, SqliteConnection};
+use diesel::{define_sql_function, Connection, connection::SimpleConnection, ExpressionMethods, NullableExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper, SqliteConnection};
+use chrono::NaiveDateTime;
 use diesel::associations::HasTable;
 use dotenv::dotenv;
 use tokio::task;
-use crate::model::securitymodel::EncodeJWT;
-use crate::model::usermodel::{ConversionError, CreateUserRequest, LoginRequest, User};
+use crate::model::usermodel::{ConversionError, CreateUserRequest, User};
 use crate::schema::users::dsl::*;
 
 pub fn establish_connection() -> SqliteConnection {
     dotenv().ok();
 
     let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    SqliteConnection::establish(&database_url)
-        .unwrap_or_else(|_| panic!("Error connecting to {}", database_url))
+    let mut connection = SqliteConnection::establish(&database_url)
+        .unwrap_or_else(|_| panic!("Error connecting to {}", database_url));
+    // Concurrent write transactions wait for each other instead of failing with `database is locked`. SQLite
+    // only enforces foreign keys and runs their ON DELETE actions when each connection asks for it.
+    connection.batch_execute("PRAGMA busy_timeout = 5000; PRAGMA foreign_keys = ON;")
+        .unwrap_or_else(|_| panic!("Error configuring {}", database_url));
+    connection
 }
 
 
-pub async fn create_user(new_user: CreateUserRequest) -> Result<bool, Error>{
+/// Returns the created user, or `None` when name or email are taken.
+pub async fn create_user(new_user: CreateUserRequest) -> Result<Option<User>, Error>{
     let res = task::spawn_blocking(move || {
         let connection =  &mut establish_connection();
         diesel::insert_into(users::table())
             .values(new_user)
+            .returning(User::as_select())
             .get_result::<User>( connection)
 
 
     }).await;
     
   match res { 
-      Ok(Ok(user)) => {
-          println!("{:?}", user);
-          Ok(true)
-      }
+      Ok(Ok(user)) => Ok(Some(user)),
       Ok(Err(_diesel_error)) => {
           println!("Database Error");
-          Ok(false)
+          Ok(None)
       }
       Err(_join_error) => { // Outer Err for a tokio::task::JoinError
           println!("Error with Thread");
@@ -45,51 +49,98 @@ pub async fn create_user(new_user: CreateUserRequest) -> Result<bool, Error>{
     
 }
 
-pub async fn check_if_user_exist(user: EncodeJWT) -> Result<bool,ConversionError>{
-    
+pub async fn get_user_by_id(user_id: i32) -> Result<Option<User>,ConversionError>{
+
     let res = task::spawn_blocking(move || {
         let connection = &mut establish_connection();
-        
-        users.count().filter(email.eq(user.email)).limit(1).get_result::<i64>( connection)
+
+        users.filter(id.eq(user_id)).select(User::as_select()).first::<User>(connection).optional()
     }).await?;
-    
-    let res = match res {
-        Ok(count) => {count}
-        Err(_) => {
-            println!("Error: {}", Error);
-            0
+
+    match res {
+        Ok(user) => Ok(user),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error with DB".to_string()))
         }
-    };
-    if res > 0{
-        Ok(true)
     }
-    else { 
-        Err(ConversionError::ConversionError("Error with DB".to_string()))
+}
+
+define_sql_function!(fn lower(value: diesel::sql_types::Text) -> diesel::sql_types::Text);
+
+/// Looks the user up by email when the identifier contains an `@`, by name otherwise. Both ignore case.
+pub async fn get_user_by_identifier(identifier: String) -> Result<Option<User>,ConversionError>{
+
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+
+        let identifier = identifier.trim().to_lowercase();
+        if identifier.contains('@') {
+            users.filter(lower(email).eq(identifier)).select(User::as_select()).first::<User>(connection).optional()
+        } else {
+            users.filter(lower(name).eq(identifier)).select(User::as_select()).first::<User>(connection).optional()
+        }
+    }).await?;
+
+    match res {
+        Ok(user) => Ok(user),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error with DB".to_string()))
+        }
     }
 }
 
-pub async fn check_if_user_exist_login(user: LoginRequest) -> Result<bool,ConversionError>{
+pub async fn update_user_password(user_id: i32, password_hash: String) -> Result<(),ConversionError>{
 
     let res = task::spawn_blocking(move || {
         let connection = &mut establish_connection();
 
-        users.count().filter(name.eq(user.name)).filter(password.eq(user.password)).limit(1).get_result::<i64>( connection)
+        diesel::update(users.filter(id.eq(user_id))).set(password.eq(password_hash)).execute(connection)
     }).await?;
 
-    let res = match res {
-        Ok(count) => {count}
-        Err(_) => {
-            println!("Error: {}", Error);
-            0
+    match res {
+        Ok(_) => Ok(()),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error with DB".to_string()))
         }
-    };
-    if res > 0{
-        Ok(true)
     }
-    else {
-        Err(ConversionError::ConversionError("Error with DB".to_string()))
+}
+
+/// Invalidates every access token issued to the user up to now.
+pub async fn set_tokens_valid_after(user_id: i32, valid_after: NaiveDateTime) -> Result<(),ConversionError>{
+
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
+
+        diesel::update(users.filter(id.eq(user_id))).set(tokens_valid_after.eq(valid_after)).execute(connection)
+    }).await?;
+
+    match res {
+        Ok(_) => Ok(()),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error with DB".to_string()))
+        }
     }
 }
 
+pub async fn set_email_verified(user_id: i32) -> Result<(),ConversionError>{
+
+    let res = task::spawn_blocking(move || {
+        let connection = &mut establish_connection();
 
+        diesel::update(users.filter(id.eq(user_id)).filter(email_verified_at.is_null()))
+            .set(email_verified_at.eq(diesel::dsl::now.nullable()))
+            .execute(connection)
+    }).await?;
 
+    match res {
+        Ok(_) => Ok(()),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError::ConversionError("Error with DB".to_string()))
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use diesel::{BoolExpressionMethods, Connection, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper};
+use diesel::result::Error as DieselError;
+use tokio::task;
+use crate::model::filemodel::Blob;
+use crate::model::usermodel::{ConversionError, File};
+use crate::model::usermodel::ConversionError::*;
+use crate::model::versionmodel::{FileVersion, FileVersionToInsert, VersionRetention};
+use crate::repository::filerepository::release_blob_reference;
+use crate::repository::userrepository::establish_connection;
+use crate::schema::{blob, file, file_version};
+
+fn load_live_file(conn: &mut diesel::SqliteConnection, other_id: i32) -> Result<Option<File>, DieselError> {
+    file::table
+        .filter(file::id.eq(other_id))
+        .filter(file::is_deleted.is_null().or(file::is_deleted.ne(1)))
+        .select(File::as_select())
+        .first::<File>(conn)
+        .optional()
+}
+
+/// Moves the current content of `current` into the history and makes the given content current under the next number.
+fn make_current(conn: &mut diesel::SqliteConnection, current: &File, content_hash: String, content_type: String, size: i64, storage_path: String) -> Result<File, DieselError> {
+    diesel::insert_into(file_version::table)
+        .values(FileVersionToInsert::from(current))
+        .execute(conn)?;
+    diesel::update(file::table.filter(file::id.eq(current.id)))
+        .set((
+            file::content_hash.eq(content_hash),
+            file::content_type.eq(content_type),
+            file::size.eq(size),
+            file::storage_path.eq(storage_path),
+            file::version.eq(current.version + 1),
+        ))
+        .execute(conn)?;
+    // Read back afterwards, RETURNING would not see the `updated_at` the trigger wrote.
+    file::table
+        .filter(file::id.eq(current.id))
+        .select(File::as_select())
+        .first::<File>(conn)
+}
+
+/// Makes `new_blob` the current content of a live file, the previous content becomes a version and keeps its
+/// blob reference. Returns `None` when the file is gone or in the trash.
+pub async fn add_file_version(other_id: i32, new_blob: Blob, new_content_type: String) -> Result<Option<File>, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let mut conn = establish_connection();
+
+        conn.transaction::<_, DieselError, _>(|conn| {
+            let Some(current) = load_live_file(conn, other_id)? else {
+                return Ok(None)
+            };
+            make_current(conn, &current, new_blob.content_hash, new_content_type, new_blob.size, new_blob.storage_path).map(Some)
+        })
+    }).await?;
+
+    match res {
+        Ok(updated) => Ok(updated),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError("Error adding File version".to_string()))
+        }
+    }
+}
+
+/// Replaced versions of a file, newest first.
+pub async fn list_file_versions(other_file_id: i32) -> Result<Vec<FileVersion>, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let mut conn = establish_connection();
+        file_version::table
+            .filter(file_version::file_id.eq(other_file_id))
+            .order_by(file_version::version.desc())
+            .select(FileVersion::as_select())
+            .load::<FileVersion>(&mut conn)
+    }).await?;
+
+    match res {
+        Ok(versions) => Ok(versions),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError("Error listing File versions".to_string()))
+        }
+    }
+}
+
+pub async fn get_file_version(other_file_id: i32, other_version: i32) -> Result<Option<FileVersion>, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let mut conn = establish_connection();
+        file_version::table
+            .filter(file_version::file_id.eq(other_file_id))
+            .filter(file_version::version.eq(other_version))
+            .select(FileVersion::as_select())
+            .first::<FileVersion>(&mut conn)
+            .optional()
+    }).await?;
+
+    match res {
+        Ok(found) => Ok(found),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError("Error loading File version".to_string()))
+        }
+    }
+}
+
+/// Makes the content of `other_version` current again as a new version, nothing is lost from the history.
+/// Returns `None` when the live file or the version does not exist.
+pub async fn revert_to_version(other_file_id: i32, other_version: i32) -> Result<Option<File>, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let mut conn = establish_connection();
+
+        conn.transaction::<_, DieselError, _>(|conn| {
+            let Some(current) = load_live_file(conn, other_file_id)? else {
+                return Ok(None)
+            };
+            let Some(restored) = file_version::table
+                .filter(file_version::file_id.eq(other_file_id))
+                .filter(file_version::version.eq(other_version))
+                .select(FileVersion::as_select())
+                .first::<FileVersion>(conn)
+                .optional()? else {
+                return Ok(None)
+            };
+
+            // The file row becomes a second reference to the restored blob, next to the version row.
+            diesel::update(blob::table.filter(blob::storage_path.eq(&restored.storage_path)))
+                .set(blob::ref_count.eq(blob::ref_count + 1))
+                .execute(conn)?;
+            make_current(conn, &current, restored.content_hash, restored.content_type, restored.size, restored.storage_path).map(Some)
+        })
+    }).await?;
+
+    match res {
+        Ok(reverted) => Ok(reverted),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError("Error reverting File".to_string()))
+        }
+    }
+}
+
+/// Deletes the versions `retention` no longer keeps and drops their blob references. Returns the storage
+/// paths nothing references anymore, the caller deletes the objects.
+pub async fn prune_file_versions(retention: VersionRetention) -> Result<Vec<String>, ConversionError> {
+    let res = task::spawn_blocking(move || {
+        let mut conn = establish_connection();
+
+        conn.transaction::<_, DieselError, _>(|conn| {
+            let versions = file_version::table
+                .order_by((file_version::file_id.asc(), file_version::version.desc()))
+                .select(FileVersion::as_select())
+                .load::<FileVersion>(conn)?;
+
+            let mut unreferenced = Vec::new();
+            let mut newer_of_file = 0;
+            let mut previous_file_id = None;
+            for archived in versions {
+                if previous_file_id != Some(archived.file_id) {
+                    previous_file_id = Some(archived.file_id);
+                    newer_of_file = 0;
+                }
+                let beyond_last = retention.keep_last.is_some_and(|keep_last| newer_of_file >= keep_last);
+                let too_old = matches!((retention.replaced_before, archived.replaced_at), (Some(cutoff), Some(replaced_at)) if replaced_at < cutoff);
+                newer_of_file += 1;
+                if !beyond_last && !too_old {
+                    continue
+                }
+
+                diesel::delete(file_version::table.filter(file_version::id.eq(archived.id))).execute(conn)?;
+                if release_blob_reference(conn, &archived.storage_path)? && !unreferenced.contains(&archived.storage_path) {
+                    unreferenced.push(archived.storage_path);
+                }
+            }
+            Ok(unreferenced)
+        })
+    }).await?;
+
+    match res {
+        Ok(paths) => Ok(paths),
+        Err(diesel_error) => {
+            println!("Diesel ORM Error: {}", diesel_error);
+            Err(ConversionError("Error pruning File versions".to_string()))
+        }
+    }
+}
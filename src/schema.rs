@@ -1,5 +1,32 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    api_key (id) {
+        id -> Nullable<Integer>,
+        user_id -> Integer,
+        name -> Text,
+        prefix -> Text,
+        key_hash -> Text,
+        scopes -> Text,
+        created_at -> Nullable<Timestamp>,
+        last_used_at -> Nullable<Timestamp>,
+        expires_at -> Nullable<Timestamp>,
+        revoked_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    blob (id) {
+        id -> Nullable<Integer>,
+        content_hash -> Text,
+        hash_algorithm -> Text,
+        storage_path -> Text,
+        size -> BigInt,
+        ref_count -> Integer,
+        created_at -> Nullable<Timestamp>,
+    }
+}
+
 diesel::table! {
     file (id) {
         id -> Nullable<Integer>,
@@ -7,7 +34,7 @@ diesel::table! {
         hashed_file_name -> Text,
         content_hash -> Text,
         content_type -> Text,
-        size -> Integer,
+        size -> BigInt,
         storage_path -> Text,
         owner_id -> Nullable<Integer>,
         is_public -> Nullable<Integer>,
@@ -15,6 +42,8 @@ diesel::table! {
         created_at -> Nullable<Timestamp>,
         updated_at -> Nullable<Timestamp>,
         deleted_at -> Nullable<Timestamp>,
+        folder_id -> Nullable<Integer>,
+        version -> Integer,
     }
 }
 
@@ -23,6 +52,140 @@ diesel::table! {
         id -> Nullable<Integer>,
         link -> Nullable<Text>,
         filename -> Nullable<Text>,
+        file_id -> Nullable<Integer>,
+        expires_at -> Nullable<Timestamp>,
+        password_hash -> Nullable<Text>,
+        max_downloads -> Nullable<Integer>,
+        download_count -> Integer,
+        revoked_at -> Nullable<Timestamp>,
+        created_by -> Nullable<Integer>,
+        created_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    file_version (id) {
+        id -> Nullable<Integer>,
+        file_id -> Integer,
+        version -> Integer,
+        content_hash -> Text,
+        content_type -> Text,
+        size -> BigInt,
+        storage_path -> Text,
+        replaced_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    folder (id) {
+        id -> Nullable<Integer>,
+        owner_id -> Integer,
+        parent_id -> Nullable<Integer>,
+        name -> Text,
+        path -> Text,
+        created_at -> Nullable<Timestamp>,
+        updated_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    login_lockout (id) {
+        id -> Nullable<Integer>,
+        throttle_key -> Text,
+        ip_address -> Nullable<Text>,
+        failures -> Integer,
+        locked_until -> Timestamp,
+        created_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    login_throttle (throttle_key) {
+        throttle_key -> Text,
+        failures -> Integer,
+        last_failure_at -> Timestamp,
+        blocked_until -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    mfa_recovery_code (id) {
+        id -> Nullable<Integer>,
+        user_id -> Integer,
+        code_hash -> Text,
+        used_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    refresh_token (id) {
+        id -> Nullable<Integer>,
+        user_id -> Integer,
+        token_hash -> Text,
+        family_id -> Text,
+        expires_at -> Timestamp,
+        created_at -> Nullable<Timestamp>,
+        used_at -> Nullable<Timestamp>,
+        revoked_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    revoked_token (jti) {
+        jti -> Text,
+        user_id -> Integer,
+        expires_at -> Timestamp,
+        revoked_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    session (id) {
+        id -> Nullable<Integer>,
+        user_id -> Integer,
+        family_id -> Text,
+        user_agent -> Nullable<Text>,
+        ip_address -> Nullable<Text>,
+        created_at -> Nullable<Timestamp>,
+        last_seen_at -> Nullable<Timestamp>,
+        expires_at -> Timestamp,
+        revoked_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    tus_upload (id) {
+        id -> Nullable<Integer>,
+        upload_id -> Text,
+        file_name -> Text,
+        content_type -> Nullable<Text>,
+        upload_length -> BigInt,
+        upload_offset -> BigInt,
+        owner_id -> Nullable<Integer>,
+        created_at -> Nullable<Timestamp>,
+        updated_at -> Nullable<Timestamp>,
+        on_conflict -> Text,
+        folder_id -> Nullable<Integer>,
+    }
+}
+
+diesel::table! {
+    tus_upload_part (id) {
+        id -> Nullable<Integer>,
+        upload_id -> Text,
+        part_offset -> BigInt,
+        part_key -> Text,
+        size -> BigInt,
+    }
+}
+
+diesel::table! {
+    user_mfa (user_id) {
+        user_id -> Integer,
+        totp_secret -> Text,
+        confirmed_at -> Nullable<Timestamp>,
+        last_used_step -> Nullable<BigInt>,
+        created_at -> Nullable<Timestamp>,
     }
 }
 
@@ -32,13 +195,41 @@ diesel::table! {
         name -> Text,
         email -> Text,
         password -> Text,
+        tokens_valid_after -> Nullable<Timestamp>,
+        is_admin -> Integer,
+        email_verified_at -> Nullable<Timestamp>,
     }
 }
 
+diesel::joinable!(api_key -> users (user_id));
+diesel::joinable!(file -> folder (folder_id));
 diesel::joinable!(file -> users (owner_id));
+diesel::joinable!(file_to_link -> file (file_id));
+diesel::joinable!(file_to_link -> users (created_by));
+diesel::joinable!(file_version -> file (file_id));
+diesel::joinable!(folder -> users (owner_id));
+diesel::joinable!(mfa_recovery_code -> users (user_id));
+diesel::joinable!(refresh_token -> users (user_id));
+diesel::joinable!(revoked_token -> users (user_id));
+diesel::joinable!(session -> users (user_id));
+diesel::joinable!(tus_upload -> users (owner_id));
+diesel::joinable!(user_mfa -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    api_key,
+    blob,
     file,
     file_to_link,
+    file_version,
+    folder,
+    login_lockout,
+    login_throttle,
+    mfa_recovery_code,
+    refresh_token,
+    revoked_token,
+    session,
+    tus_upload,
+    tus_upload_part,
+    user_mfa,
     users,
 );
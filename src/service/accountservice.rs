@@ -0,0 +1,145 @@
+use std::env;
+use chrono::DateTime;
+use dotenv::dotenv;
+use sha2::{Digest, Sha256};
+use crate::mail::mailer::mailer;
+use crate::model::mailmodel::MailMessage;
+use crate::model::securitymodel::PurposeClaims;
+use crate::model::usermodel::{ConversionError, User};
+use crate::model::usermodel::ConversionError::*;
+use crate::repository::userrepository::{get_user_by_id, get_user_by_identifier, set_email_verified, update_user_password};
+use crate::Security::jwt::{decode_purpose_token, encode_purpose_token, EMAIL_VERIFICATION_AUDIENCE, PASSWORD_RESET_AUDIENCE};
+use crate::Security::password::hash_password;
+use crate::Security::revocation::{is_token_revoked, revoke_token};
+use crate::service::tokenservice::revoke_all_tokens_for_user;
+
+fn env_ttl(name: &str, default: i64) -> i64 {
+    dotenv().ok();
+    env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|ttl| *ttl > 0)
+        .unwrap_or(default)
+}
+
+/// Lifetime of verification links in seconds, read from `EMAIL_VERIFICATION_TTL` (defaults to 24 hours).
+fn email_verification_ttl() -> i64 {
+    env_ttl("EMAIL_VERIFICATION_TTL", 24 * 60 * 60)
+}
+
+/// Lifetime of reset links in seconds, read from `PASSWORD_RESET_TTL` (defaults to 1 hour).
+fn password_reset_ttl() -> i64 {
+    env_ttl("PASSWORD_RESET_TTL", 60 * 60)
+}
+
+/// Whether unverified accounts are refused at login, read from `REQUIRE_EMAIL_VERIFICATION` (defaults to true).
+pub fn require_email_verification() -> bool {
+    dotenv().ok();
+    env::var("REQUIRE_EMAIL_VERIFICATION").map_or(true, |value| value != "false" && value != "0")
+}
+
+/// Base of the links put into mails, read from `PUBLIC_BASE_URL`. The pages behind them post the token to the API.
+fn public_base_url() -> String {
+    dotenv().ok();
+    env::var("PUBLIC_BASE_URL")
+        .unwrap_or_else(|_| "http://localhost:3000".to_string())
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// Ties reset tokens to the password they replace, every outstanding link dies with the first reset.
+fn password_fingerprint(user: &User) -> String {
+    hex::encode(&Sha256::digest(user.password.as_bytes())[..16])
+}
+
+fn user_id_of(user: &User) -> Result<i32, ConversionError> {
+    user.id.ok_or_else(|| ConversionError("User has no id".to_string()))
+}
+
+/// Checks the token and that it was not used yet, then loads its user.
+async fn redeem_token(token: &str, audience: &str) -> Result<(PurposeClaims, User), ConversionError> {
+    let claims = decode_purpose_token(token, audience)?;
+    if is_token_revoked(&claims.jti).await? {
+        return Err(Unauthorized("Token was already used".to_string()))
+    }
+    let user_id = claims.sub.parse::<i32>()
+        .map_err(|_| Unauthorized("Invalid subject in token".to_string()))?;
+    let user = get_user_by_id(user_id).await?
+        .ok_or_else(|| Unauthorized("User does not exist anymore".to_string()))?;
+    Ok((claims, user))
+}
+
+async fn spend_token(claims: PurposeClaims, user_id: i32) -> Result<(), ConversionError> {
+    let expires_at = DateTime::from_timestamp(claims.exp, 0)
+        .ok_or_else(|| Unauthorized("Invalid expiry in token".to_string()))?
+        .naive_utc();
+    revoke_token(claims.jti, user_id, expires_at).await
+}
+
+pub async fn send_verification_email(user: &User) -> Result<(), ConversionError> {
+    let token = encode_purpose_token(user_id_of(user)?, EMAIL_VERIFICATION_AUDIENCE, email_verification_ttl(), None)?;
+    mailer().await?.send(MailMessage {
+        to: user.email.clone(),
+        subject: "Confirm your email address".to_string(),
+        body: format!(
+            "Hello {},\n\nplease confirm your email address by opening this link:\n\n{}/verify-email?token={}\n\nThe link is valid for {} hours.\n",
+            user.name, public_base_url(), token, email_verification_ttl() / 3600,
+        ),
+    }).await?;
+    Ok(())
+}
+
+pub async fn verify_email(token: &str) -> Result<(), ConversionError> {
+    let (claims, user) = redeem_token(token, EMAIL_VERIFICATION_AUDIENCE).await?;
+    let user_id = user_id_of(&user)?;
+    set_email_verified(user_id).await?;
+    spend_token(claims, user_id).await
+}
+
+/// Always succeeds and returns at once, the lookup and the mail happen in the background so neither
+/// the answer nor its timing tells whether an account has this address.
+pub fn forgot_password(email: String) {
+    tokio::spawn(async move {
+        if let Err(error) = send_password_reset(email).await {
+            println!("Password reset mail failed: {}", error);
+        }
+    });
+}
+
+async fn send_password_reset(email: String) -> Result<(), ConversionError> {
+    if !email.contains('@') {
+        return Ok(())
+    }
+    let Some(user) = get_user_by_identifier(email).await? else {
+        return Ok(())
+    };
+    let token = encode_purpose_token(user_id_of(&user)?, PASSWORD_RESET_AUDIENCE, password_reset_ttl(), Some(password_fingerprint(&user)))?;
+    mailer().await?.send(MailMessage {
+        to: user.email.clone(),
+        subject: "Reset your password".to_string(),
+        body: format!(
+            "Hello {},\n\na password reset was requested for your account. Choose a new password here:\n\n{}/reset-password?token={}\n\nThe link is valid for {} minutes. If you did not ask for this, ignore this mail.\n",
+            user.name, public_base_url(), token, password_reset_ttl() / 60,
+        ),
+    }).await?;
+    Ok(())
+}
+
+/// Sets the new password and logs the user out everywhere. Receiving the mail proves the address,
+/// so the account counts as verified afterwards.
+pub async fn reset_password(token: &str, password: String) -> Result<(), ConversionError> {
+    if password.is_empty() {
+        return Err(BadRequest("password must not be empty".to_string()))
+    }
+    let (claims, user) = redeem_token(token, PASSWORD_RESET_AUDIENCE).await?;
+    if claims.fpr.as_deref() != Some(password_fingerprint(&user).as_str()) {
+        return Err(Unauthorized("Token is no longer valid".to_string()))
+    }
+    let user_id = user_id_of(&user)?;
+
+    let password_hash = tokio::task::spawn_blocking(move || hash_password(&password)).await??;
+    update_user_password(user_id, password_hash).await?;
+    spend_token(claims, user_id).await?;
+    set_email_verified(user_id).await?;
+    revoke_all_tokens_for_user(user_id).await
+}
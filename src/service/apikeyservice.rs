@@ -0,0 +1,95 @@
+use chrono::{Duration, Utc};
+use sha2::{Digest, Sha256};
+use crate::model::apikeymodel::{ApiKey, ApiKeyResponse, ApiKeyToInsert, CreateApiKeyRequest, CreatedApiKeyResponse};
+use crate::model::securitymodel::{AuthUser, Scope};
+use crate::model::usermodel::{ConversionError, User};
+use crate::model::usermodel::ConversionError::*;
+use crate::repository::apikeyrepository::{get_api_key_by_hash, insert_api_key, list_api_keys_for_user, revoke_api_key, touch_api_key};
+use crate::repository::userrepository::get_user_by_id;
+use crate::Security::randomtoken::random_base62;
+
+/// Marks API keys so they are recognisable in logs and by secret scanners.
+pub const API_KEY_PREFIX: &str = "fsk_";
+/// 40 base62 characters are about 238 bits.
+const API_KEY_SECRET_LENGTH: usize = 40;
+/// Characters of the key kept in clear to tell keys apart in listings.
+const API_KEY_DISPLAY_LENGTH: usize = 12;
+const API_KEY_NAME_MAX_LENGTH: usize = 100;
+
+/// Keys carry enough entropy that a plain SHA-256 is sufficient at rest, no slow hash needed.
+fn hash_api_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+pub async fn create_api_key(user: &AuthUser, request: CreateApiKeyRequest) -> Result<CreatedApiKeyResponse, ConversionError> {
+    // A leaked key must not be able to mint further keys.
+    user.require_login()?;
+
+    let name = request.name.trim().to_string();
+    if name.is_empty() || name.chars().count() > API_KEY_NAME_MAX_LENGTH {
+        return Err(BadRequest(format!("name must have 1 to {} characters", API_KEY_NAME_MAX_LENGTH)))
+    }
+    let mut scopes: Vec<Scope> = Vec::new();
+    for scope in request.scopes {
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+    if scopes.is_empty() {
+        return Err(BadRequest("scopes must not be empty".to_string()))
+    }
+    if let Some(scope) = scopes.iter().find(|scope| !user.has_scope(**scope)) {
+        return Err(Forbidden(format!("Cannot grant scope {} you do not have", scope.as_str())))
+    }
+    let expires_at = match request.expires_in {
+        Some(seconds) if seconds <= 0 => return Err(BadRequest("expires_in must be positive".to_string())),
+        Some(seconds) => Some(Utc::now().naive_utc() + Duration::seconds(seconds)),
+        None => None,
+    };
+
+    let key = format!("{}{}", API_KEY_PREFIX, random_base62(API_KEY_SECRET_LENGTH));
+    let created = insert_api_key(ApiKeyToInsert {
+        user_id: user.id,
+        name,
+        prefix: key[..API_KEY_DISPLAY_LENGTH].to_string(),
+        key_hash: hash_api_key(&key),
+        scopes: Scope::join(&scopes),
+        expires_at,
+    }).await?;
+
+    Ok(CreatedApiKeyResponse { key, api_key: ApiKeyResponse::from(created) })
+}
+
+pub async fn list_api_keys(user: &AuthUser) -> Result<Vec<ApiKeyResponse>, ConversionError> {
+    // Key management is for the account holder, a key must not see or revoke its siblings.
+    user.require_login()?;
+    let keys = list_api_keys_for_user(user.id).await?;
+    Ok(keys.into_iter().map(ApiKeyResponse::from).collect())
+}
+
+pub async fn revoke_user_api_key(user: &AuthUser, key_id: i32) -> Result<(), ConversionError> {
+    user.require_login()?;
+    if !revoke_api_key(key_id, user.id).await? {
+        return Err(NotFound(format!("API key {}", key_id)))
+    }
+    Ok(())
+}
+
+/// Looks up the key of an `Authorization: ApiKey ...` header and its owner, rejecting revoked and expired keys.
+pub async fn resolve_api_key(key: &str) -> Result<(ApiKey, User), ConversionError> {
+    let stored = get_api_key_by_hash(hash_api_key(key)).await?
+        .ok_or_else(|| Unauthorized("Unknown API key".to_string()))?;
+    let key_id = stored.id.ok_or_else(|| ConversionError("API key has no id".to_string()))?;
+
+    if stored.revoked_at.is_some() {
+        return Err(Unauthorized("API key was revoked".to_string()))
+    }
+    if matches!(stored.expires_at, Some(expires_at) if expires_at <= Utc::now().naive_utc()) {
+        return Err(Unauthorized("API key expired".to_string()))
+    }
+
+    let user = get_user_by_id(stored.user_id).await?
+        .ok_or_else(|| Unauthorized("User does not exist anymore".to_string()))?;
+    touch_api_key(key_id).await?;
+    Ok((stored, user))
+}
@@ -1,135 +1,504 @@
-use std::fmt::Error;
-use std::fs::File;
-use std::io::Write;
-use bytes::Bytes;
-use aws_sdk_s3::primitives::ByteStream;
+use std::env;
 use axum::extract::Multipart;
-use bcrypt::hash;
-use crate::model::filemodel::GetFileResponse;
-use crate::model::usermodel::{ConversionError, FileToInsert};
+use axum::extract::multipart::Field;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use dotenv::dotenv;
+use futures_util::StreamExt;
+use sha2::{Digest, Sha256};
+use crate::model::filemodel::{Blob, BlobToInsert, ConflictPolicy, FileCursor, FileListFilter, FileListResponse, FileResponse, FileSort, GetFileResponse, ListFilesQuery, UpdateFileRequest};
+use crate::model::securitymodel::AuthUser;
+use crate::model::usermodel::{ConversionError, File, FileToInsert};
 use crate::model::usermodel::ConversionError::*;
-use crate::repository::filerepository::{check_if_file_name_exists, get_file_name_from_db, write_name_to_db};
+use crate::model::storagemodel::StorageError;
+use crate::Security::filename::{file_extension, sanitize_file_name};
+use crate::Security::randomtoken::generate_share_token;
+use crate::model::sharemodel::CreateShareLinkRequest;
+use crate::repository::filerepository::{find_sibling_file, get_blob_by_path, get_file_by_id, list_deleted_files, list_files_for_owner, purge_file, release_blob, set_file_deleted, store_blob, update_file_metadata, write_name_to_db, FILE_TOKEN_TAKEN};
+use crate::repository::versionrepository::add_file_version;
+use crate::service::folderservice::load_owned_folder;
+use crate::service::shareservice::{create_share_link, resolve_share_link, SHARE_TOKEN_ATTEMPTS};
+use crate::storage::storagebackend::{storage_backend, ByteStream};
 
+/// Page size of `GET /api/files` when the client does not ask for one.
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
 
-pub async fn store_files(mut file: Multipart) -> Result<Vec<String>,ConversionError>{
-    let mut links = Vec::new();
+/// Upper bound for a single uploaded file in bytes, read from `MAX_UPLOAD_SIZE` (defaults to 5 GiB).
+pub fn max_upload_size() -> u64 {
+    dotenv().ok();
+    env::var("MAX_UPLOAD_SIZE")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(5 * 1024 * 1024 * 1024)
+}
 
-    while let Some(field) = file.next_field().await? {
-        let mut content_type = String::new();
+/// Digest used for new uploads, read from `CONTENT_HASH_ALGORITHM` (`sha256` or `blake3`, defaults to `sha256`).
+pub fn content_hash_algorithm() -> String {
+    dotenv().ok();
+    match env::var("CONTENT_HASH_ALGORITHM").map(|value| value.to_lowercase()) {
+        Ok(value) if value == "blake3" => "blake3".to_string(),
+        _ => "sha256".to_string(),
+    }
+}
 
-        let other_file_name = field.name().unwrap().to_string();
-        
-        let check = check_if_file_name_exists(other_file_name.clone()).await?;
-        
-      
-        
-        let file_type = field.content_type();
+enum ContentHasher {
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
 
-        match file_type {
-            Some(file_type) => {
-                let filetype_splited:Vec<&str> = file_type.split("/").collect();
-                content_type = filetype_splited[1].to_string();
-            }
-            None => {
-                content_type = "txt".to_string();
-            }
-        }
+/// Running content digest and byte count of an upload, enforcing the size limit while the data streams through.
+pub struct UploadDigest {
+    hasher: ContentHasher,
+    received: u64,
+    limit: u64,
+}
 
-        println!("went to after contenttype");
+impl UploadDigest {
+    pub fn new(algorithm: &str, limit: u64) -> Self {
+        let hasher = match algorithm {
+            "blake3" => ContentHasher::Blake3(Box::new(blake3::Hasher::new())),
+            _ => ContentHasher::Sha256(Sha256::new()),
+        };
+        UploadDigest { hasher, received: 0, limit }
+    }
 
-        let filename = "content/".to_owned() + other_file_name.as_str() + &"." + &content_type;
-        let data = field.bytes().await.map_err(ConversionError::from)?;
+    pub fn update(&mut self, chunk: &[u8]) -> Result<(), StorageError> {
+        self.received += chunk.len() as u64;
+        if self.received > self.limit {
+            return Err(StorageError::TooLarge(self.limit))
+        }
+        match &mut self.hasher {
+            ContentHasher::Sha256(hasher) => hasher.update(chunk),
+            ContentHasher::Blake3(hasher) => { hasher.update(chunk); }
+        }
+        Ok(())
+    }
 
-        println!("Went after Data");
-        println!("{}", filename);
-
-        let size = data.len();
-        let size = size.try_into()?;
-
-        println!("Length of `{:?}` is {} bytes", other_file_name, data.len());
-        let name_link_hash = hash(filename.clone(), 4)?;
-        let data_hash = hash(data.clone(),4)?;
-
-
-        let file_struct: FileToInsert = FileToInsert {
-            file_name: other_file_name.clone(),
-            hashed_file_name: name_link_hash.clone(),
-            content_hash: data_hash.clone(),
-            content_type: content_type.clone(),
-            size: size,
-            storage_path: filename.clone(),
-            owner_id: None,
-            is_public: Some(1),
-            is_deleted: Some(0),
+    /// Returns the byte count and the hex encoded digest.
+    pub fn finalize(self) -> (u64, String) {
+        let digest = match self.hasher {
+            ContentHasher::Sha256(hasher) => hex::encode(hasher.finalize()),
+            ContentHasher::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
         };
+        (self.received, digest)
+    }
+}
 
-        aws(&data, &file_struct).await?;
-        write_data(&data, &file_struct).await?;
+/// Derives the stored content type from the MIME type of an upload.
+pub fn content_type_of(file_type: Option<&str>) -> String {
+    match file_type {
+        Some(file_type) => {
+            let filetype_splited:Vec<&str> = file_type.split("/").collect();
+            filetype_splited.get(1).unwrap_or(&filetype_splited[0]).to_string()
+        }
+        None => {
+            "txt".to_string()
+        }
+    }
+}
 
-       
-        let other_link = create_link(file_struct).await?;
-        links.push(other_link)
+/// Content type of an upload: the declared MIME type, or the one the extension suggests when the client
+/// declared none or just `application/octet-stream`.
+pub fn upload_content_type(declared: Option<&str>, file_name: &str) -> String {
+    let specific = declared.filter(|declared| *declared != "application/octet-stream");
+    content_type_of(specific.or_else(|| mime_guess::from_path(file_name).first_raw()).or(declared))
+}
+
+/// The name the client gave the uploaded file, sanitized. Fields without one fall back to the field name.
+fn upload_file_name(field: &Field<'_>) -> Result<String, ConversionError> {
+    field.file_name()
+        .or(field.name())
+        .and_then(sanitize_file_name)
+        .ok_or_else(|| BadRequest("Upload has no usable file name".to_string()))
+}
+
+/// Streams `data` into storage and deduplicates it by content digest. When identical content is already
+/// stored, the new copy is dropped and the returned blob points at the existing object.
+pub async fn store_content(data: ByteStream<'_>) -> Result<Blob, ConversionError> {
+    let storage = storage_backend().await;
+    let algorithm = content_hash_algorithm();
+    let key = format!("blobs/{}", uuid::Uuid::new_v4().simple());
+
+    // Hash and count while the chunks pass through, the upload is never held in memory as a whole.
+    let mut digest = UploadDigest::new(&algorithm, max_upload_size());
+    let body = data.map(|chunk| {
+        let chunk = chunk?;
+        digest.update(&chunk)?;
+        Ok(chunk)
+    });
+    storage.put_stream(&key, Box::pin(body)).await?;
+
+    let (size, content_hash) = digest.finalize();
+    let registered = match size.try_into() {
+        Ok(size) => store_blob(BlobToInsert {
+            content_hash,
+            hash_algorithm: algorithm,
+            storage_path: key.clone(),
+            size,
+        }).await,
+        Err(error) => Err(ConversionError::from(error)),
+    };
+    // Without a blob row nothing would ever reference or clean up the stored object.
+    let stored_blob = match registered {
+        Ok(stored_blob) => stored_blob,
+        Err(error) => {
+            delete_stored_object(&key).await?;
+            return Err(error)
+        }
+    };
+
+    if stored_blob.storage_path != key {
+        println!("Content already stored, dropping duplicate");
+        storage.delete(&key).await?;
     }
-    Ok(links)
+    Ok(stored_blob)
+}
+
+/// How many `name (n).ext` candidates `ConflictPolicy::Rename` tries before giving up.
+const MAX_RENAME_ATTEMPTS: u32 = 100;
+
+/// `report.pdf` becomes `report (2).pdf`. Dot files like `.env` count as having no extension.
+fn numbered_file_name(name: &str, number: u32) -> String {
+    match file_extension(name) {
+        Some(extension) => format!("{} ({}){}", &name[..name.len() - extension.len()], number, extension),
+        None => format!("{} ({})", name, number),
+    }
+}
+
+fn file_name_conflict(name: &str) -> ConversionError {
+    Conflict(format!("A file named {} already exists in this folder, upload with on_conflict=rename or on_conflict=version to keep both", name))
+}
+
+/// Refuses a name that is taken in the folder, so a rejected upload is not stored first.
+pub async fn check_file_name_available(owner_id: i32, folder_id: Option<i32>, name: &str) -> Result<(), ConversionError> {
+    if find_sibling_file(owner_id, folder_id, name.to_string(), None).await?.is_some() {
+        return Err(file_name_conflict(name))
+    }
+    Ok(())
+}
+
+/// Removes an object from storage, one that is already gone counts as removed.
+pub async fn delete_stored_object(path: &str) -> Result<(), ConversionError> {
+    match storage_backend().await.delete(path).await {
+        Ok(()) | Err(StorageError::NotFound(_)) => Ok(()),
+        Err(error) => Err(error.into())
+    }
+}
+
+/// Gives back the reference a stored upload holds when no file ends up using it.
+pub async fn discard_blob(path: String) -> Result<(), ConversionError> {
+    if let Some(path) = release_blob(path).await? {
+        delete_stored_object(&path).await?;
+    }
+    Ok(())
 }
 
-pub async fn create_link(file:FileToInsert) -> Result<String,ConversionError>{
+/// Creates the `file` row for a stored blob, owned by `other_owner_id` and put into `folder_id`, and returns its
+/// download link. A live file of the same name in that folder is handled as `on_conflict` says.
+pub async fn register_file(other_file_name: String, content_type: String, stored_blob: Blob, other_owner_id: i32, folder_id: Option<i32>, on_conflict: ConflictPolicy) -> Result<String, ConversionError> {
+    let size = stored_blob.size;
 
+    if on_conflict == ConflictPolicy::Version
+        && let Some(existing) = find_sibling_file(other_owner_id, folder_id, other_file_name.clone(), None).await? {
+        return replace_with_version(existing, stored_blob, content_type).await
+    }
 
-    println!("File: {:?}", file);
-    let file = write_name_to_db(file).await;
+    let storage_path = stored_blob.storage_path.clone();
+    let file_struct: FileToInsert = FileToInsert {
+        file_name: other_file_name,
+        hashed_file_name: String::new(),
+        content_hash: stored_blob.content_hash,
+        content_type,
+        size,
+        storage_path: stored_blob.storage_path,
+        owner_id: Some(other_owner_id),
+        is_public: Some(1),
+        is_deleted: Some(0),
+        folder_id,
+    };
 
-    let files;
-    match file {
-        Ok(file) => {
-            files = file
+    let inserted = match on_conflict {
+        ConflictPolicy::Rename => insert_file_with_free_name(file_struct, other_owner_id).await,
+        ConflictPolicy::Reject | ConflictPolicy::Version => insert_file(file_struct).await,
+    };
+    match inserted {
+        Ok(inserted) => create_link(&inserted).await,
+        Err(error) => {
+            discard_blob(storage_path).await?;
+            Err(error)
         }
-        Err(_) => {
-            return Err(ConversionError("error".to_string()))
+    }
+}
+
+/// `ConflictPolicy::Version`: the existing file gets the new content as its next version, the previous content
+/// stays in the version history. Its id and share links stay.
+async fn replace_with_version(existing: File, stored_blob: Blob, content_type: String) -> Result<String, ConversionError> {
+    let file_id = existing.id.unwrap_or_default();
+    let new_path = stored_blob.storage_path.clone();
+    let replaced = match add_file_version(file_id, stored_blob, content_type).await {
+        Ok(Some(replaced)) => replaced,
+        Ok(None) => {
+            discard_blob(new_path).await?;
+            return Err(NotFound(format!("File {}", file_id)))
+        }
+        Err(error) => {
+            discard_blob(new_path).await?;
+            return Err(error)
         }
     };
-    println!("Filename: {}", &files.hashed_file_name);
-    let other_link = format!("localhost:3000/api/download/{}", urlencoding::encode(files.hashed_file_name.as_str()));
+    create_link(&replaced).await
+}
+
+pub async fn store_files(mut file: Multipart, owner: &AuthUser, folder_id: Option<i32>, on_conflict: ConflictPolicy) -> Result<Vec<String>,ConversionError>{
+    let mut links = Vec::new();
+    if let Some(folder_id) = folder_id {
+        load_owned_folder(folder_id, owner).await?;
+    }
+
+    while let Some(field) = file.next_field().await? {
+        let other_file_name = upload_file_name(&field)?;
+        
+        if on_conflict == ConflictPolicy::Reject {
+            check_file_name_available(owner.id, folder_id, &other_file_name).await?;
+        }
+        
+        let content_type = upload_content_type(field.content_type(), &other_file_name);
+
+        let body = field.map(|chunk| chunk.map_err(|e| StorageError::Backend(e.to_string())));
+        let stored_blob = store_content(Box::pin(body)).await?;
+
+        println!("Went after Data");
+
+        let other_link = register_file(other_file_name, content_type, stored_blob, owner.id, folder_id, on_conflict).await?;
+        links.push(other_link)
+    }
+    Ok(links)
+}
+
+/// Inserts the row under a fresh `hashed_file_name`, retrying on the rare token collision.
+/// A taken file name is reported with a hint at the other conflict policies.
+async fn insert_file(mut file: FileToInsert) -> Result<File, ConversionError> {
+    println!("Storing File {}", file.file_name);
+
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        file.hashed_file_name = generate_share_token();
+        match write_name_to_db(file.clone()).await {
+            Ok(file) => return Ok(file),
+            Err(Conflict(message)) if message == FILE_TOKEN_TAKEN && attempt < SHARE_TOKEN_ATTEMPTS => {
+                println!("File token collision, retrying");
+            }
+            Err(Conflict(message)) if message != FILE_TOKEN_TAKEN => return Err(file_name_conflict(&file.file_name)),
+            Err(error) => return Err(error)
+        }
+    }
+}
+
+/// `ConflictPolicy::Rename`: the first of `name`, `name (1)`, `name (2)`, ... that is free in the folder.
+async fn insert_file_with_free_name(mut file: FileToInsert, other_owner_id: i32) -> Result<File, ConversionError> {
+    let requested_name = file.file_name.clone();
+    for number in 0..=MAX_RENAME_ATTEMPTS {
+        let candidate = if number == 0 { requested_name.clone() } else { numbered_file_name(&requested_name, number) };
+        if find_sibling_file(other_owner_id, file.folder_id, candidate.clone(), None).await?.is_some() {
+            continue
+        }
+        file.file_name = candidate;
+        match insert_file(file.clone()).await {
+            // Taken by a concurrent upload since the check, try the next number.
+            Err(Conflict(message)) if message != FILE_TOKEN_TAKEN => continue,
+            result => return result,
+        }
+    }
+    Err(Conflict(format!("No free name left for {} in this folder", requested_name)))
+}
+
+pub async fn create_link(files: &File) -> Result<String,ConversionError>{
+    println!("Creating default link for File {:?}", files.id);
+
+    // Every upload gets an unrestricted share link that can be revoked like any other one.
+    let share = create_share_link(files, CreateShareLinkRequest::default(), files.owner_id).await?;
+    let other_link = format!("localhost:3000/api/download/{}", share.link.unwrap_or_default());
     Ok(other_link)
 
 }
 
 
-pub async fn get_file_name(file_link: String) -> Result<GetFileResponse,Error> { // In Futur add checking for Same Name of File
+pub async fn get_file_name(file_link: String, password: Option<&str>, client_ip: Option<&str>) -> Result<GetFileResponse,ConversionError> { // In Futur add checking for Same Name of File
     let file_link: Vec<_> = file_link.split("/").collect();
-    let file_name_hash = file_link[file_link.len() - 1];
+    let share_token = file_link[file_link.len() - 1];
 
-    let file = get_file_name_from_db(file_name_hash.to_string()).await?;
+    let (share, file) = resolve_share_link(share_token.to_string(), password, client_ip).await?;
 
-    let file_names = &file[0].file_name;
-    let file_paths = &file[0].storage_path;
+    let stored_blob = get_blob_by_path(file.storage_path.clone()).await?;
 
     let res:GetFileResponse = GetFileResponse{
-        filename: file_names.to_string(),
-        filepath: file_paths.to_string()
+        filename: file.file_name.to_string(),
+        filepath: file.storage_path.to_string(),
+        content_type: file.content_type.to_string(),
+        content_hash: file.content_hash.to_string(),
+        hash_algorithm: stored_blob.map(|stored_blob| stored_blob.hash_algorithm),
+        last_modified: file.updated_at.or(file.created_at),
+        share: Some(share)
     };
 
     Ok(res)
 }
 
-pub async fn aws(data: &Bytes, data_info: &FileToInsert) -> Result<(), Box<dyn std::error::Error>> {
-    let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
-    let client = aws_sdk_s3::Client::new(&config);
-    
-    client.put_object()
-        .bucket("fileshareapistorage")
-        .key(&data_info.file_name)
-        .body(ByteStream::from(data.to_vec()))
-        .send()
-        .await?;
-        
+fn encode_cursor(cursor: &FileCursor) -> Result<String, ConversionError> {
+    let json = serde_json::to_vec(cursor).map_err(|e| ConversionError(e.to_string()))?;
+    Ok(URL_SAFE_NO_PAD.encode(json))
+}
+
+/// Cursors are opaque to clients, anything that does not decode or does not fit the sort is rejected.
+fn decode_cursor(encoded: &str, sort: FileSort) -> Result<FileCursor, ConversionError> {
+    let cursor: FileCursor = URL_SAFE_NO_PAD.decode(encoded)
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .ok_or_else(|| BadRequest("Invalid cursor".to_string()))?;
+    let matches_sort = match sort {
+        FileSort::Name => cursor.name.is_some(),
+        FileSort::Size => cursor.size.is_some(),
+        FileSort::CreatedAt => cursor.created_at.is_some(),
+    };
+    if !matches_sort {
+        return Err(BadRequest("Cursor does not belong to this sort".to_string()))
+    }
+    Ok(cursor)
+}
+
+pub async fn list_files(owner: &AuthUser, query: ListFilesQuery) -> Result<FileListResponse, ConversionError> {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return Err(BadRequest(format!("limit must be between 1 and {}", MAX_PAGE_SIZE)))
+    }
+    if query.min_size.is_some_and(|size| size < 0) || query.max_size.is_some_and(|size| size < 0) {
+        return Err(BadRequest("Size filters must not be negative".to_string()))
+    }
+    let after = query.cursor.as_deref()
+        .map(|cursor| decode_cursor(cursor, query.sort))
+        .transpose()?;
+
+    // One extra row tells whether there is a next page.
+    let mut files = list_files_for_owner(FileListFilter {
+        owner_id: owner.id,
+        sort: query.sort,
+        order: query.order,
+        after,
+        limit: limit + 1,
+        content_type: query.content_type.as_deref().map(|content_type| content_type_of(Some(content_type))),
+        min_size: query.min_size,
+        max_size: query.max_size,
+        created_after: query.created_after,
+        created_before: query.created_before,
+        is_public: query.is_public,
+    }).await?;
+
+    let next_cursor = if files.len() as i64 > limit {
+        files.truncate(limit as usize);
+        match files.last() {
+            Some(last) => Some(encode_cursor(&FileCursor {
+                id: last.id.unwrap_or_default(),
+                name: (query.sort == FileSort::Name).then(|| last.file_name.clone()),
+                size: (query.sort == FileSort::Size).then_some(last.size),
+                created_at: if query.sort == FileSort::CreatedAt { last.created_at } else { None },
+            })?),
+            None => None,
+        }
+    } else {
+        None
+    };
+
+    Ok(FileListResponse {
+        files: files.into_iter().map(FileResponse::from).collect(),
+        next_cursor,
+    })
+}
+
+pub async fn load_file(file_id: i32) -> Result<File, ConversionError> {
+    get_file_by_id(file_id).await?
+        .ok_or_else(|| NotFound(format!("File {}", file_id)))
+}
+
+/// Like `load_file`, but files of other users are reported as missing.
+pub async fn load_owned_file(file_id: i32, owner: &AuthUser) -> Result<File, ConversionError> {
+    let owned_file = load_file(file_id).await?;
+    if owned_file.owner_id != Some(owner.id) {
+        return Err(NotFound(format!("File {}", file_id)))
+    }
+    Ok(owned_file)
+}
+
+/// Checks a name given for a rename. Unlike uploads, a name with slashes is refused rather than cut down.
+fn validate_file_name(name: &str) -> Result<String, ConversionError> {
+    if name.contains(['/', '\\']) {
+        return Err(BadRequest("File name must not contain slashes".to_string()))
+    }
+    sanitize_file_name(name).ok_or_else(|| BadRequest("File name must not be empty, . or ..".to_string()))
+}
+
+/// Renames and/or moves a file. Another file of the same name in the target folder is a conflict.
+pub async fn update_file(owner: &AuthUser, file_id: i32, request: UpdateFileRequest) -> Result<FileResponse, ConversionError> {
+    let existing = load_owned_file(file_id, owner).await?;
+    if existing.is_deleted == Some(1) {
+        return Err(NotFound(format!("File {}", file_id)))
+    }
+    let other_file_name = match request.file_name {
+        Some(name) => validate_file_name(&name)?,
+        None => existing.file_name.clone(),
+    };
+    let folder_id = request.folder_id.unwrap_or(existing.folder_id);
+    if let Some(folder_id) = folder_id {
+        load_owned_folder(folder_id, owner).await?;
+    }
+
+    if find_sibling_file(owner.id, folder_id, other_file_name.clone(), Some(file_id)).await?.is_some() {
+        return Err(Conflict(format!("A file named {} already exists in this folder", other_file_name)))
+    }
+    let updated = update_file_metadata(file_id, owner.id, other_file_name, folder_id).await?
+        .ok_or_else(|| NotFound(format!("File {}", file_id)))?;
+    Ok(FileResponse::from(updated))
+}
+
+/// Moves a file into the trash. Its share links stop resolving until it is restored.
+pub async fn delete_file(owner: &AuthUser, file_id: i32) -> Result<(), ConversionError> {
+    load_owned_file(file_id, owner).await?;
+    // Already trashed files are left alone, deleting twice is not an error.
+    set_file_deleted(file_id, owner.id, true).await?;
     Ok(())
 }
 
-pub async fn write_data(data: &Bytes, data_info: &FileToInsert) -> Result<(), ConversionError>{
+pub async fn list_trash(owner: &AuthUser) -> Result<Vec<FileResponse>, ConversionError> {
+    let files = list_deleted_files(owner.id).await?;
+    Ok(files.into_iter().map(FileResponse::from).collect())
+}
+
+pub async fn restore_file(owner: &AuthUser, file_id: i32) -> Result<FileResponse, ConversionError> {
+    if !set_file_deleted(file_id, owner.id, false).await? {
+        return Err(NotFound(format!("File {} in trash", file_id)))
+    }
+    Ok(FileResponse::from(load_file(file_id).await?))
+}
+
+/// Deletes a trashed file for good and removes its object from storage once no other file shares it.
+pub async fn purge_trashed_file(owner: &AuthUser, file_id: i32) -> Result<(), ConversionError> {
+    let trashed_file = load_owned_file(file_id, owner).await?;
+    if trashed_file.is_deleted != Some(1) {
+        return Err(NotFound(format!("File {} in trash", file_id)))
+    }
+
+    for path in purge_file(file_id).await? {
+        delete_stored_object(&path).await?;
+    }
+    Ok(())
+}
 
-    let mut file = File::create(data_info.file_name.clone()).map_err(|e| ConversionError::ConversionError("Error Creating File".to_string()))?;
-    file.write(&*data).map_err(|e| ConversionError::ConversionError("Error writing Data to File".to_string()))?;
- 
+pub async fn empty_trash(owner: &AuthUser) -> Result<(), ConversionError> {
+    for trashed_file in list_deleted_files(owner.id).await? {
+        if let Some(file_id) = trashed_file.id {
+            purge_trashed_file(owner, file_id).await?;
+        }
+    }
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,92 @@
+use crate::model::filemodel::FileResponse;
+use crate::model::foldermodel::{folder_path, CreateFolderRequest, Folder, FolderListingResponse, FolderResponse, FolderToInsert, FolderUpdate, UpdateFolderRequest};
+use crate::model::securitymodel::AuthUser;
+use crate::model::usermodel::ConversionError;
+use crate::model::usermodel::ConversionError::*;
+use crate::repository::filerepository::list_files_in_folder;
+use crate::repository::folderrepository::{delete_folder_tree, get_folder_by_id, insert_folder, list_child_folders, update_folder as other_update_folder};
+
+const MAX_FOLDER_NAME_LENGTH: usize = 255;
+
+/// Trims the name and rejects anything that could not be a single path segment.
+fn validate_folder_name(name: &str) -> Result<String, ConversionError> {
+    let name = name.trim();
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(BadRequest("Folder name must not be empty, . or ..".to_string()))
+    }
+    if name.chars().count() > MAX_FOLDER_NAME_LENGTH {
+        return Err(BadRequest(format!("Folder name must not be longer than {} characters", MAX_FOLDER_NAME_LENGTH)))
+    }
+    if name.chars().any(|c| c == '/' || c == '\\' || c.is_control()) {
+        return Err(BadRequest("Folder name must not contain slashes or control characters".to_string()))
+    }
+    Ok(name.to_string())
+}
+
+/// Folders of other users are reported as missing.
+pub async fn load_owned_folder(folder_id: i32, owner: &AuthUser) -> Result<Folder, ConversionError> {
+    get_folder_by_id(folder_id).await?
+        .filter(|found| found.owner_id == owner.id)
+        .ok_or_else(|| NotFound(format!("Folder {}", folder_id)))
+}
+
+async fn load_parent(parent_id: Option<i32>, owner: &AuthUser) -> Result<Option<Folder>, ConversionError> {
+    match parent_id {
+        Some(parent_id) => Ok(Some(load_owned_folder(parent_id, owner).await?)),
+        None => Ok(None),
+    }
+}
+
+pub async fn create_folder(owner: &AuthUser, request: CreateFolderRequest) -> Result<FolderResponse, ConversionError> {
+    let name = validate_folder_name(&request.name)?;
+    let parent = load_parent(request.parent_id, owner).await?;
+
+    let created = insert_folder(FolderToInsert {
+        owner_id: owner.id,
+        parent_id: request.parent_id,
+        path: folder_path(parent.as_ref(), &name),
+        name,
+    }).await?;
+    Ok(FolderResponse::from(created))
+}
+
+/// Renames and/or moves a folder. A folder can not be moved into itself or one of its subfolders.
+pub async fn update_folder(owner: &AuthUser, folder_id: i32, request: UpdateFolderRequest) -> Result<FolderResponse, ConversionError> {
+    let existing = load_owned_folder(folder_id, owner).await?;
+    let name = match request.name {
+        Some(name) => validate_folder_name(&name)?,
+        None => existing.name.clone(),
+    };
+    let parent_id = request.parent_id.unwrap_or(existing.parent_id);
+    load_parent(parent_id, owner).await?;
+
+    match other_update_folder(folder_id, name, parent_id).await? {
+        FolderUpdate::Updated(updated) => Ok(FolderResponse::from(updated)),
+        FolderUpdate::FolderMissing => Err(NotFound(format!("Folder {}", folder_id))),
+        FolderUpdate::ParentMissing => Err(NotFound(format!("Folder {}", parent_id.unwrap_or_default()))),
+        FolderUpdate::IntoItself => Err(BadRequest("A folder can not be moved into itself".to_string())),
+    }
+}
+
+/// Deleting a folder that still has content needs `recursive`, its files then go to the trash.
+pub async fn delete_folder(owner: &AuthUser, folder_id: i32, recursive: bool) -> Result<(), ConversionError> {
+    load_owned_folder(folder_id, owner).await?;
+    match delete_folder_tree(folder_id, recursive).await? {
+        Some(true) => Ok(()),
+        Some(false) => Err(Conflict("Folder is not empty, delete it with recursive=true".to_string())),
+        None => Err(NotFound(format!("Folder {}", folder_id))),
+    }
+}
+
+/// Subfolders and files directly inside `folder_id`, or at the top level when it is `None`.
+pub async fn list_folder(owner: &AuthUser, folder_id: Option<i32>) -> Result<FolderListingResponse, ConversionError> {
+    let listed = load_parent(folder_id, owner).await?;
+    let folders = list_child_folders(owner.id, folder_id).await?;
+    let files = list_files_in_folder(owner.id, folder_id).await?;
+
+    Ok(FolderListingResponse {
+        folder: listed.map(FolderResponse::from),
+        folders: folders.into_iter().map(FolderResponse::from).collect(),
+        files: files.into_iter().map(FileResponse::from).collect(),
+    })
+}
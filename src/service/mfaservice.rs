@@ -0,0 +1,161 @@
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use crate::model::mfamodel::{MfaLoginRequest, RecoveryCodeToInsert, RecoveryCodesResponse, TotpEnrollmentResponse, UserMfa, UserMfaToInsert};
+use crate::model::securitymodel::AuthUser;
+use crate::model::sessionmodel::SessionClient;
+use crate::model::throttlemodel::LoginThrottleKeys;
+use crate::model::usermodel::{ConversionError, LoginResponse};
+use crate::model::usermodel::ConversionError::*;
+use crate::repository::mfarepository::{confirm_user_mfa, delete_user_mfa, get_user_mfa, replace_recovery_codes, replace_unconfirmed_user_mfa, use_recovery_code, use_totp_step};
+use crate::repository::userrepository::get_user_by_id;
+use crate::Security::jwt::{decode_mfa_challenge, jwt_issuer};
+use crate::Security::randomtoken::random_base62;
+use crate::Security::revocation::{is_token_revoked, revoke_token};
+use crate::Security::totp::{decode_totp_secret, encode_totp_secret, generate_totp_secret, is_unused_step, otpauth_uri, verify_totp};
+use crate::service::throttleservice::{record_login_failure, record_login_success, reserve_login_attempt};
+use crate::service::tokenservice::issue_tokens;
+
+const RECOVERY_CODE_COUNT: usize = 10;
+/// Characters per recovery code, shown split in two halves.
+const RECOVERY_CODE_LENGTH: usize = 10;
+
+/// Dashes and case are ignored so codes can be typed the way they are read.
+fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    hex::encode(Sha256::digest(normalized.as_bytes()))
+}
+
+fn generate_recovery_codes(user_id: i32) -> (Vec<String>, Vec<RecoveryCodeToInsert>) {
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let code = random_base62(RECOVERY_CODE_LENGTH).to_ascii_lowercase();
+            format!("{}-{}", &code[..RECOVERY_CODE_LENGTH / 2], &code[RECOVERY_CODE_LENGTH / 2..])
+        })
+        .collect();
+    let hashes = codes.iter()
+        .map(|code| RecoveryCodeToInsert { user_id, code_hash: hash_recovery_code(code) })
+        .collect();
+    (codes, hashes)
+}
+
+fn now_unix() -> u64 {
+    Utc::now().timestamp().max(0) as u64
+}
+
+fn totp_secret_of(mfa: &UserMfa) -> Result<Vec<u8>, ConversionError> {
+    decode_totp_secret(&mfa.totp_secret).ok_or_else(|| ConversionError("Stored TOTP secret is invalid".to_string()))
+}
+
+async fn confirmed_mfa(user_id: i32) -> Result<UserMfa, ConversionError> {
+    get_user_mfa(user_id).await?
+        .filter(|mfa| mfa.confirmed_at.is_some())
+        .ok_or_else(|| NotFound("Two-factor authentication is not enabled".to_string()))
+}
+
+/// Accepts a TOTP code that was not used before, or an unused recovery code which is then spent.
+async fn check_second_factor(mfa: &UserMfa, code: &str) -> Result<bool, ConversionError> {
+    if let Some(step) = verify_totp(&totp_secret_of(mfa)?, code, now_unix()) {
+        if !is_unused_step(step, mfa.last_used_step) {
+            return Ok(false)
+        }
+        return use_totp_step(mfa.user_id, step as i64).await
+    }
+    use_recovery_code(mfa.user_id, hash_recovery_code(code)).await
+}
+
+/// `check_second_factor` charged to the user's login budget, so codes can not be guessed faster anywhere than at login.
+async fn check_throttled_second_factor(mfa: &UserMfa, code: &str, keys: &LoginThrottleKeys) -> Result<bool, ConversionError> {
+    let attempt = reserve_login_attempt(keys).await?;
+    if !check_second_factor(mfa, code).await? {
+        record_login_failure(attempt).await?;
+        return Ok(false)
+    }
+    record_login_success(attempt).await?;
+    Ok(true)
+}
+
+pub async fn user_requires_mfa(user_id: i32) -> Result<bool, ConversionError> {
+    Ok(get_user_mfa(user_id).await?.is_some_and(|mfa| mfa.confirmed_at.is_some()))
+}
+
+/// Generates a new secret. Nothing changes for logins until the secret is confirmed with a code.
+pub async fn start_totp_enrollment(user: &AuthUser) -> Result<TotpEnrollmentResponse, ConversionError> {
+    user.require_login()?;
+    let secret = encode_totp_secret(&generate_totp_secret());
+    let replaced = replace_unconfirmed_user_mfa(UserMfaToInsert { user_id: user.id, totp_secret: secret.clone() }).await?;
+    if !replaced {
+        return Err(Conflict("Two-factor authentication is already enabled".to_string()))
+    }
+    Ok(TotpEnrollmentResponse {
+        otpauth_uri: otpauth_uri(&jwt_issuer(), &user.name, &secret),
+        secret,
+    })
+}
+
+/// Enables MFA once the user proves their authenticator produces the right codes.
+pub async fn confirm_totp_enrollment(user: &AuthUser, code: &str) -> Result<RecoveryCodesResponse, ConversionError> {
+    user.require_login()?;
+    let mfa = get_user_mfa(user.id).await?
+        .ok_or_else(|| NotFound("No two-factor enrollment was started".to_string()))?;
+    if mfa.confirmed_at.is_some() {
+        return Err(Conflict("Two-factor authentication is already enabled".to_string()))
+    }
+    let step = verify_totp(&totp_secret_of(&mfa)?, code, now_unix())
+        .ok_or_else(|| BadRequest("Invalid code".to_string()))?;
+
+    let (recovery_codes, hashes) = generate_recovery_codes(user.id);
+    if !confirm_user_mfa(user.id, step as i64, hashes).await? {
+        return Err(Conflict("Two-factor authentication is already enabled".to_string()))
+    }
+    Ok(RecoveryCodesResponse { recovery_codes })
+}
+
+pub async fn disable_mfa(user: &AuthUser, code: &str, client_ip: Option<&str>) -> Result<(), ConversionError> {
+    user.require_login()?;
+    let mfa = confirmed_mfa(user.id).await?;
+    if !check_throttled_second_factor(&mfa, code, &LoginThrottleKeys::for_account(user.id, client_ip)).await? {
+        return Err(Forbidden("Invalid code".to_string()))
+    }
+    delete_user_mfa(user.id).await
+}
+
+pub async fn regenerate_recovery_codes(user: &AuthUser, code: &str, client_ip: Option<&str>) -> Result<RecoveryCodesResponse, ConversionError> {
+    user.require_login()?;
+    let mfa = confirmed_mfa(user.id).await?;
+    if !check_throttled_second_factor(&mfa, code, &LoginThrottleKeys::for_account(user.id, client_ip)).await? {
+        return Err(Forbidden("Invalid code".to_string()))
+    }
+    let (recovery_codes, hashes) = generate_recovery_codes(user.id);
+    replace_recovery_codes(user.id, hashes).await?;
+    Ok(RecoveryCodesResponse { recovery_codes })
+}
+
+/// Second step of a login with MFA. The challenge token is spent on success so it cannot start a second session.
+pub async fn complete_mfa_login(request: MfaLoginRequest, client: SessionClient) -> Result<LoginResponse, ConversionError> {
+    let claims = decode_mfa_challenge(&request.mfa_token)?;
+    if is_token_revoked(&claims.jti).await? {
+        return Err(Unauthorized("MFA token was already used".to_string()))
+    }
+    let user_id = claims.sub.parse::<i32>()
+        .map_err(|_| Unauthorized("Invalid subject in MFA token".to_string()))?;
+    let user = get_user_by_id(user_id).await?
+        .ok_or_else(|| Unauthorized("User does not exist anymore".to_string()))?;
+    let mfa = confirmed_mfa(user_id).await
+        .map_err(|_| Unauthorized("Two-factor authentication is not enabled".to_string()))?;
+
+    // Codes are guessed against the same budget as passwords.
+    let keys = LoginThrottleKeys::new(&user.name, Some(&user), client.ip_address.as_deref());
+    if !check_throttled_second_factor(&mfa, &request.code, &keys).await? {
+        return Err(Unauthorized("Invalid code".to_string()))
+    }
+
+    let expires_at = DateTime::from_timestamp(claims.exp, 0)
+        .ok_or_else(|| Unauthorized("Invalid expiry in MFA token".to_string()))?
+        .naive_utc();
+    revoke_token(claims.jti, user_id, expires_at).await?;
+
+    issue_tokens(&user, client).await
+}
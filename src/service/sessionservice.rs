@@ -0,0 +1,53 @@
+use std::env;
+use std::net::SocketAddr;
+use axum::http::{header, HeaderMap};
+use dotenv::dotenv;
+use crate::model::securitymodel::AuthUser;
+use crate::model::sessionmodel::{Session, SessionClient, SessionResponse};
+use crate::model::usermodel::ConversionError;
+use crate::model::usermodel::ConversionError::*;
+use crate::repository::sessionrepository::{get_session, list_active_sessions, revoke_session};
+use crate::repository::tokenrepository::revoke_refresh_token_family;
+
+/// Whether the client address is taken from `X-Forwarded-For`, read from `TRUST_FORWARDED_FOR`.
+/// Only enable this behind a proxy that sets the header, clients can send anything there.
+fn trust_forwarded_for() -> bool {
+    dotenv().ok();
+    env::var("TRUST_FORWARDED_FOR").is_ok_and(|value| value == "true" || value == "1")
+}
+
+pub fn session_client(headers: &HeaderMap, peer: SocketAddr) -> SessionClient {
+    let forwarded = trust_forwarded_for()
+        .then(|| headers.get("X-Forwarded-For").and_then(|value| value.to_str().ok()))
+        .flatten()
+        .and_then(|value| value.split(',').next())
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+
+    SessionClient {
+        user_agent: headers.get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string),
+        ip_address: Some(forwarded.unwrap_or_else(|| peer.ip().to_string())),
+    }
+}
+
+/// Ends a session: its refresh tokens stop working and `authenticate` rejects access tokens carrying its id.
+pub async fn end_session(session: &Session) -> Result<(), ConversionError> {
+    let session_id = session.id.ok_or_else(|| ConversionError("Session has no id".to_string()))?;
+    revoke_session(session_id, session.user_id).await?;
+    revoke_refresh_token_family(session.family_id.clone()).await?;
+    Ok(())
+}
+
+pub async fn list_sessions(user: &AuthUser) -> Result<Vec<SessionResponse>, ConversionError> {
+    let sessions = list_active_sessions(user.id).await?;
+    Ok(sessions.into_iter().map(|session| SessionResponse::new(session, user.session_id())).collect())
+}
+
+pub async fn revoke_user_session(user: &AuthUser, session_id: i32) -> Result<(), ConversionError> {
+    let session = get_session(session_id).await?
+        .filter(|session| session.user_id == user.id && session.revoked_at.is_none())
+        .ok_or_else(|| NotFound(format!("Session {}", session_id)))?;
+    end_session(&session).await
+}
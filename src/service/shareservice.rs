@@ -0,0 +1,132 @@
+use chrono::{Duration, Utc};
+use tokio::task;
+use crate::model::securitymodel::AuthUser;
+use crate::model::sharemodel::{CreateShareLinkRequest, ShareLink, ShareLinkResponse, ShareLinkToInsert};
+use crate::model::throttlemodel::LoginThrottleKeys;
+use crate::model::usermodel::{ConversionError, File};
+use crate::model::usermodel::ConversionError::*;
+use crate::repository::sharerepository::{count_download, get_share_link, get_share_link_by_id, insert_share_link, list_share_links_for_file, revoke_share_link};
+use crate::service::fileservice::{load_file, load_owned_file};
+use crate::service::throttleservice::{record_login_failure, record_login_success, reserve_login_attempt};
+use crate::Security::password::{hash_password, verify_password};
+use crate::Security::randomtoken::generate_share_token;
+
+/// How often a freshly drawn share token may collide with an existing one before giving up.
+pub const SHARE_TOKEN_ATTEMPTS: usize = 5;
+
+pub async fn create_share_link(shared_file: &File, request: CreateShareLinkRequest, created_by: Option<i32>) -> Result<ShareLink, ConversionError> {
+    let file_id = shared_file.id.ok_or_else(|| ConversionError("File has no id".to_string()))?;
+
+    let expires_at = match request.expires_in {
+        Some(seconds) if seconds <= 0 => return Err(BadRequest("expires_in must be positive".to_string())),
+        Some(seconds) => Some(Utc::now().naive_utc() + Duration::seconds(seconds)),
+        None => None,
+    };
+    if matches!(request.max_downloads, Some(limit) if limit <= 0) {
+        return Err(BadRequest("max_downloads must be positive".to_string()))
+    }
+    let password_hash = match request.password.as_deref() {
+        Some("") => return Err(BadRequest("password must not be empty".to_string())),
+        Some(password) => {
+            // Argon2 is deliberately expensive, keep it off the async workers.
+            let password = password.to_string();
+            Some(task::spawn_blocking(move || hash_password(&password)).await??)
+        }
+        None => None,
+    };
+
+    let mut new_link = ShareLinkToInsert {
+        link: String::new(),
+        filename: shared_file.file_name.clone(),
+        file_id,
+        expires_at,
+        password_hash,
+        max_downloads: request.max_downloads,
+        created_by,
+        created_at: Utc::now().naive_utc(),
+    };
+
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        new_link.link = generate_share_token();
+        match insert_share_link(new_link.clone()).await {
+            Ok(share) => return Ok(share),
+            Err(Conflict(_)) if attempt < SHARE_TOKEN_ATTEMPTS => {
+                println!("Share token collision, retrying");
+            }
+            Err(error) => return Err(error)
+        }
+    }
+}
+
+pub async fn create_share_link_for_file(owner: &AuthUser, file_id: i32, request: CreateShareLinkRequest) -> Result<ShareLinkResponse, ConversionError> {
+    let shared_file = load_owned_file(file_id, owner).await?;
+    if shared_file.is_deleted == Some(1) {
+        return Err(NotFound(format!("File {}", file_id)))
+    }
+    let share = create_share_link(&shared_file, request, Some(owner.id)).await?;
+    Ok(ShareLinkResponse::from(share))
+}
+
+pub async fn list_share_links(owner: &AuthUser, file_id: i32) -> Result<Vec<ShareLinkResponse>, ConversionError> {
+    load_owned_file(file_id, owner).await?;
+    let links = list_share_links_for_file(file_id).await?;
+    Ok(links.into_iter().map(ShareLinkResponse::from).collect())
+}
+
+/// Revoking twice is fine, the first revocation time is kept.
+pub async fn revoke_link(owner: &AuthUser, link_id: i32) -> Result<(), ConversionError> {
+    let share = get_share_link_by_id(link_id).await?
+        .ok_or_else(|| NotFound(format!("Share link {}", link_id)))?;
+    let file_id = share.file_id.ok_or_else(|| NotFound(format!("Share link {}", link_id)))?;
+    load_owned_file(file_id, owner).await
+        .map_err(|_| NotFound(format!("Share link {}", link_id)))?;
+    revoke_share_link(link_id).await?;
+    Ok(())
+}
+
+/// Looks up the link behind a download token and checks the password, then revocation, expiry and the download
+/// limit. Failed passwords are throttled per link and per `client_ip` with the login backoff.
+pub async fn resolve_share_link(token: String, password: Option<&str>, client_ip: Option<&str>) -> Result<(ShareLink, File), ConversionError> {
+    let share = get_share_link(token.clone()).await?
+        .ok_or(NotFound(token))?;
+
+    // Checked first, so without the password nobody learns whether the link was revoked, expired or used up.
+    if let Some(password_hash) = share.password_hash.as_deref() {
+        let link_id = share.id.ok_or_else(|| ConversionError("Share link has no id".to_string()))?;
+        // Opening the link without any password is how clients learn one is needed, not a guess.
+        let password = password.ok_or_else(|| Unauthorized("Share link requires a valid password".to_string()))?;
+        let attempt = reserve_login_attempt(&LoginThrottleKeys::for_share_link(link_id, client_ip)).await?;
+        let (password, password_hash) = (password.to_string(), password_hash.to_string());
+        if !task::spawn_blocking(move || verify_password(&password, &password_hash).valid).await? {
+            record_login_failure(attempt).await?;
+            return Err(Unauthorized("Share link requires a valid password".to_string()))
+        }
+        record_login_success(attempt).await?;
+    }
+
+    if share.revoked_at.is_some() {
+        return Err(Gone("Share link was revoked".to_string()))
+    }
+    if matches!(share.expires_at, Some(expires_at) if expires_at <= Utc::now().naive_utc()) {
+        return Err(Gone("Share link expired".to_string()))
+    }
+    if matches!(share.max_downloads, Some(limit) if share.download_count >= limit) {
+        return Err(Gone("Share link reached its download limit".to_string()))
+    }
+    let file_id = share.file_id.ok_or_else(|| NotFound("Share link has no file".to_string()))?;
+    let shared_file = load_file(file_id).await?;
+    if shared_file.is_deleted == Some(1) {
+        return Err(NotFound("File was deleted".to_string()))
+    }
+    Ok((share, shared_file))
+}
+
+pub async fn record_download(share: &ShareLink) -> Result<(), ConversionError> {
+    let link_id = share.id.ok_or_else(|| ConversionError("Share link has no id".to_string()))?;
+    if !count_download(link_id).await? {
+        return Err(Gone("Share link reached its download limit".to_string()))
+    }
+    Ok(())
+}
@@ -0,0 +1,146 @@
+use std::env;
+use chrono::{Duration, NaiveDateTime, Utc};
+use dotenv::dotenv;
+use crate::model::throttlemodel::{LoginAttempt, LoginLockoutToInsert, LoginThrottleKeys, ThrottleCharge, ThrottleReservation};
+use crate::model::usermodel::ConversionError;
+use crate::model::usermodel::ConversionError::*;
+use crate::repository::throttlerepository::{delete_login_throttle, insert_login_lockout, refund_throttled_attempt, reserve_throttled_attempt};
+
+/// How failed logins against one key are slowed down and when the key is locked.
+#[derive(Clone, Copy)]
+struct ThrottlePolicy {
+    /// Failures allowed without any delay.
+    backoff_after: i32,
+    backoff_base_seconds: i64,
+    backoff_max_seconds: i64,
+    /// Failures that lock the key for `lockout_seconds`.
+    lockout_after: i32,
+    lockout_seconds: i64,
+    /// Failures further apart than this start counting from one again.
+    window_seconds: i64,
+}
+
+fn env_i64(name: &str, default: i64) -> i64 {
+    env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|value| *value > 0)
+        .unwrap_or(default)
+}
+
+/// Read from `LOGIN_BACKOFF_AFTER`, `LOGIN_LOCKOUT_AFTER` and the shared settings.
+fn account_policy() -> ThrottlePolicy {
+    dotenv().ok();
+    ThrottlePolicy {
+        backoff_after: env_i64("LOGIN_BACKOFF_AFTER", 3) as i32,
+        lockout_after: env_i64("LOGIN_LOCKOUT_AFTER", 10) as i32,
+        ..shared_policy()
+    }
+}
+
+/// Addresses are shared behind NAT, so they get more room: `LOGIN_IP_BACKOFF_AFTER` and `LOGIN_IP_LOCKOUT_AFTER`.
+fn ip_policy() -> ThrottlePolicy {
+    dotenv().ok();
+    ThrottlePolicy {
+        backoff_after: env_i64("LOGIN_IP_BACKOFF_AFTER", 10) as i32,
+        lockout_after: env_i64("LOGIN_IP_LOCKOUT_AFTER", 50) as i32,
+        ..shared_policy()
+    }
+}
+
+/// Delays double from `LOGIN_BACKOFF_BASE_SECONDS` (1) up to `LOGIN_BACKOFF_MAX_SECONDS` (300), lockouts last
+/// `LOGIN_LOCKOUT_SECONDS` (15 minutes) and failures are forgotten after `LOGIN_FAILURE_WINDOW_SECONDS` (1 hour).
+fn shared_policy() -> ThrottlePolicy {
+    ThrottlePolicy {
+        backoff_after: 0,
+        backoff_base_seconds: env_i64("LOGIN_BACKOFF_BASE_SECONDS", 1),
+        backoff_max_seconds: env_i64("LOGIN_BACKOFF_MAX_SECONDS", 5 * 60),
+        lockout_after: 0,
+        lockout_seconds: env_i64("LOGIN_LOCKOUT_SECONDS", 15 * 60),
+        window_seconds: env_i64("LOGIN_FAILURE_WINDOW_SECONDS", 60 * 60),
+    }
+}
+
+impl ThrottlePolicy {
+    /// Until when the key is blocked after its `failures`th failure at `now`, and whether that is a lockout.
+    fn block_after(&self, failures: i32, now: NaiveDateTime) -> (Option<NaiveDateTime>, bool) {
+        if failures >= self.lockout_after {
+            return (Some(now + Duration::seconds(self.lockout_seconds)), true)
+        }
+        if failures > self.backoff_after {
+            let doublings = (failures - self.backoff_after - 1).min(30) as u32;
+            let delay = self.backoff_base_seconds.saturating_mul(1 << doublings).min(self.backoff_max_seconds);
+            return (Some(now + Duration::seconds(delay)), false)
+        }
+        (None, false)
+    }
+
+    /// Failures older than this no longer count.
+    fn window_start(&self, now: NaiveDateTime) -> NaiveDateTime {
+        now - Duration::seconds(self.window_seconds)
+    }
+}
+
+fn throttle_keys(keys: &LoginThrottleKeys) -> Vec<(String, ThrottlePolicy)> {
+    let mut throttled = vec![(keys.account.clone(), account_policy())];
+    if let Some(ip) = keys.ip() {
+        throttled.push((ip, ip_policy()));
+    }
+    throttled
+}
+
+/// Counts the attempt against all of its keys before the credentials are checked, refused while any key is backing
+/// off or locked. A blocked attempt costs nothing and answers the same for existing and unknown accounts.
+pub async fn reserve_login_attempt(keys: &LoginThrottleKeys) -> Result<LoginAttempt, ConversionError> {
+    let now = Utc::now().naive_utc();
+    let charges = throttle_keys(keys).into_iter()
+        .map(|(key, policy)| ThrottleCharge {
+            key,
+            restart_before: policy.window_start(now),
+            restart_from: policy.lockout_after,
+            block_after: Box::new(move |failures| policy.block_after(failures, now).0),
+        })
+        .collect();
+    match reserve_throttled_attempt(charges, now).await? {
+        ThrottleReservation::Reserved(reserved) => Ok(LoginAttempt { keys: keys.clone(), reserved }),
+        ThrottleReservation::Blocked(blocked_until) => {
+            // Rounded up, a client retrying after exactly this long must not be refused again.
+            let seconds = ((blocked_until - now).num_milliseconds() + 999) / 1000;
+            Err(TooManyRequests("Too many failed attempts".to_string(), seconds))
+        }
+    }
+}
+
+/// The attempt stays counted, a key it brought to a lockout gets the lockout recorded.
+pub async fn record_login_failure(attempt: LoginAttempt) -> Result<(), ConversionError> {
+    for ((key, policy), reserved) in throttle_keys(&attempt.keys).into_iter().zip(attempt.reserved) {
+        let counted = reserved.counted;
+        let (blocked_until, locked) = policy.block_after(counted.failures, counted.last_failure_at);
+        if locked && let Some(locked_until) = blocked_until {
+            println!("Login lockout for {} after {} failures, until {}", key, counted.failures, locked_until);
+            insert_login_lockout(LoginLockoutToInsert {
+                throttle_key: key,
+                ip_address: attempt.keys.ip_address.clone(),
+                failures: counted.failures,
+                locked_until,
+            }).await?;
+        }
+    }
+    Ok(())
+}
+
+/// A completed login clears the account's failures. The address only gets this attempt back, one valid account
+/// must not reset the budget of an address that is guessing at others.
+pub async fn record_login_success(attempt: LoginAttempt) -> Result<(), ConversionError> {
+    let account = attempt.keys.account;
+    let others = attempt.reserved.into_iter()
+        .filter(|reserved| reserved.counted.throttle_key != account)
+        .collect();
+    refund_throttled_attempt(others).await?;
+    delete_login_throttle(account).await
+}
+
+/// The credentials were right but the login goes on with another step, the attempt is not held against anyone.
+pub async fn refund_login_attempt(attempt: LoginAttempt) -> Result<(), ConversionError> {
+    refund_throttled_attempt(attempt.reserved).await
+}
@@ -0,0 +1,142 @@
+use std::env;
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use dotenv::dotenv;
+use sha2::{Digest, Sha256};
+use crate::model::securitymodel::{AuthUser, Credential, Scope};
+use crate::model::sessionmodel::{Session, SessionClient, SessionToInsert};
+use crate::model::tokenmodel::{LogoutRequest, RefreshTokenToInsert};
+use crate::model::usermodel::{ConversionError, LoginResponse, User};
+use crate::model::usermodel::ConversionError::*;
+use crate::repository::tokenrepository::{get_refresh_token, insert_refresh_token, revoke_refresh_token_family, revoke_refresh_tokens_for_user, use_refresh_token};
+use crate::repository::sessionrepository::{create_session, extend_session, get_session, get_session_by_family, revoke_sessions_for_user};
+use crate::repository::userrepository::{get_user_by_id, set_tokens_valid_after};
+use crate::Security::jwt::{access_token_ttl, encode_jwt};
+use crate::Security::randomtoken::random_base62;
+use crate::Security::revocation::revoke_token;
+use crate::service::sessionservice::end_session;
+
+/// Length of refresh tokens, 43 base62 characters are about 256 bits.
+const REFRESH_TOKEN_LENGTH: usize = 43;
+
+/// Lifetime of refresh tokens in seconds, read from `REFRESH_TOKEN_TTL` (defaults to 30 days).
+pub fn refresh_token_ttl() -> i64 {
+    dotenv().ok();
+    env::var("REFRESH_TOKEN_TTL")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|ttl| *ttl > 0)
+        .unwrap_or(30 * 24 * 60 * 60)
+}
+
+/// Only the digest is stored, a leaked table does not hand out usable tokens.
+pub fn hash_refresh_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+async fn issue_tokens_in_session(user: &User, session: &Session) -> Result<LoginResponse, ConversionError> {
+    let user_id = user.id.ok_or_else(|| ConversionError("User has no id".to_string()))?;
+    let session_id = session.id.ok_or_else(|| ConversionError("Session has no id".to_string()))?;
+    let jwt_token = encode_jwt(user_id, &user.name, &user.email, Some(session_id), &Scope::for_user(user))?;
+
+    let refresh_token = random_base62(REFRESH_TOKEN_LENGTH);
+    let expires_at = Utc::now().naive_utc() + Duration::seconds(refresh_token_ttl());
+    insert_refresh_token(RefreshTokenToInsert {
+        user_id,
+        token_hash: hash_refresh_token(&refresh_token),
+        family_id: session.family_id.clone(),
+        expires_at,
+    }).await?;
+    extend_session(session_id, expires_at).await?;
+
+    Ok(LoginResponse {
+        status_code: StatusCode::OK,
+        jwt_token,
+        refresh_token,
+        expires_in: access_token_ttl(),
+    })
+}
+
+async fn start_session(user_id: i32, family_id: String, client: SessionClient) -> Result<Session, ConversionError> {
+    create_session(SessionToInsert {
+        user_id,
+        family_id,
+        user_agent: client.user_agent,
+        ip_address: client.ip_address,
+        expires_at: Utc::now().naive_utc() + Duration::seconds(refresh_token_ttl()),
+    }).await
+}
+
+/// Records a new session for a fresh login and hands out its first access and refresh token.
+pub async fn issue_tokens(user: &User, client: SessionClient) -> Result<LoginResponse, ConversionError> {
+    let user_id = user.id.ok_or_else(|| ConversionError("User has no id".to_string()))?;
+    let session = start_session(user_id, uuid::Uuid::new_v4().simple().to_string(), client).await?;
+    issue_tokens_in_session(user, &session).await
+}
+
+/// Exchanges a refresh token for a new pair. Every refresh token works once; presenting one that was
+/// already exchanged means it leaked, so its session is ended and the user has to log in again.
+pub async fn refresh_tokens(refresh_token: String) -> Result<LoginResponse, ConversionError> {
+    let stored = get_refresh_token(hash_refresh_token(&refresh_token)).await?
+        .ok_or_else(|| Unauthorized("Unknown refresh token".to_string()))?;
+    let token_id = stored.id.ok_or_else(|| ConversionError("Refresh token has no id".to_string()))?;
+
+    if stored.revoked_at.is_some() {
+        return Err(Unauthorized("Refresh token was revoked".to_string()))
+    }
+    let session = get_session_by_family(stored.family_id.clone()).await?;
+    if stored.used_at.is_some() || !use_refresh_token(token_id).await? {
+        println!("Refresh token reuse detected, revoking family {}", stored.family_id);
+        match session {
+            Some(session) => end_session(&session).await?,
+            None => { revoke_refresh_token_family(stored.family_id).await?; }
+        }
+        return Err(Unauthorized("Refresh token was already used".to_string()))
+    }
+    if stored.expires_at <= Utc::now().naive_utc() {
+        return Err(Unauthorized("Refresh token expired".to_string()))
+    }
+
+    let user = get_user_by_id(stored.user_id).await?
+        .ok_or_else(|| Unauthorized("User does not exist anymore".to_string()))?;
+    let session = match session {
+        Some(session) if session.revoked_at.is_some() => return Err(Unauthorized("Session was logged out".to_string())),
+        Some(session) => session,
+        // Logins from before sessions were recorded get one on their first refresh.
+        None => start_session(stored.user_id, stored.family_id, SessionClient::default()).await?,
+    };
+    issue_tokens_in_session(&user, &session).await
+}
+
+/// Revokes the access token of the request and its session. A refresh token in the body is revoked
+/// along with its family; unknown ones or ones of other users are ignored, logging out never fails on them.
+pub async fn logout(user: &AuthUser, request: LogoutRequest) -> Result<(), ConversionError> {
+    let Credential::Jwt { token_id, expires_at, session_id } = &user.credential else {
+        return Err(BadRequest("API keys are revoked through /api/keys, not logged out".to_string()))
+    };
+    revoke_token(token_id.clone(), user.id, *expires_at).await?;
+
+    if let Some(session_id) = session_id
+        && let Some(session) = get_session(*session_id).await? {
+        end_session(&session).await?;
+    }
+    if let Some(refresh_token) = request.refresh_token {
+        let stored = get_refresh_token(hash_refresh_token(&refresh_token)).await?;
+        if let Some(stored) = stored.filter(|stored| stored.user_id == user.id) {
+            revoke_refresh_token_family(stored.family_id).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Logs the user out everywhere: every session, every access token issued so far and every refresh token.
+pub async fn revoke_all_tokens(user: &AuthUser) -> Result<(), ConversionError> {
+    revoke_all_tokens_for_user(user.id).await
+}
+
+pub async fn revoke_all_tokens_for_user(user_id: i32) -> Result<(), ConversionError> {
+    set_tokens_valid_after(user_id, Utc::now().naive_utc()).await?;
+    revoke_sessions_for_user(user_id).await?;
+    revoke_refresh_tokens_for_user(user_id).await?;
+    Ok(())
+}
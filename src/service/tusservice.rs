@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+use std::env;
+use std::time::Duration;
+use axum::body::Body;
+use axum::http::StatusCode;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::Utc;
+use dotenv::dotenv;
+use futures_util::{stream, StreamExt, TryStreamExt};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use crate::model::filemodel::ConflictPolicy;
+use crate::model::securitymodel::AuthUser;
+use crate::model::storagemodel::StorageError;
+use crate::model::tusmodel::{TusError, TusPatchResult, TusUpload, TusUploadPartToInsert, TusUploadToInsert};
+use crate::Security::filename::sanitize_file_name;
+use crate::repository::tusrepository::{advance_tus_offset, create_tus_upload, delete_tus_upload, get_tus_upload, list_stale_tus_uploads, list_tus_upload_parts};
+use crate::service::fileservice::{check_file_name_available, discard_blob, max_upload_size, register_file, store_content, upload_content_type};
+use crate::service::folderservice::load_owned_folder;
+use crate::storage::storagebackend::storage_backend;
+
+/// Status tus uses for a failed `Upload-Checksum` verification.
+const CHECKSUM_MISMATCH: u16 = 460;
+
+enum ChunkChecksum {
+    Sha1(Sha1, Vec<u8>),
+    Sha256(Sha256, Vec<u8>),
+}
+
+impl ChunkChecksum {
+    fn parse(header: &str) -> Result<Self, TusError> {
+        let (algorithm, encoded) = header.trim().split_once(' ')
+            .ok_or_else(|| TusError::new("Upload-Checksum must be `<algorithm> <base64 digest>`", StatusCode::BAD_REQUEST))?;
+        let expected = STANDARD.decode(encoded.trim())
+            .map_err(|_| TusError::new("Upload-Checksum digest is not valid base64", StatusCode::BAD_REQUEST))?;
+        match algorithm {
+            "sha1" => Ok(ChunkChecksum::Sha1(Sha1::new(), expected)),
+            "sha256" => Ok(ChunkChecksum::Sha256(Sha256::new(), expected)),
+            _ => Err(TusError::new("Unsupported checksum algorithm", StatusCode::BAD_REQUEST)),
+        }
+    }
+
+    fn update(&mut self, chunk: &[u8]) {
+        match self {
+            ChunkChecksum::Sha1(hasher, _) => hasher.update(chunk),
+            ChunkChecksum::Sha256(hasher, _) => hasher.update(chunk),
+        }
+    }
+
+    fn matches(self) -> bool {
+        match self {
+            ChunkChecksum::Sha1(hasher, expected) => hasher.finalize().as_slice() == expected.as_slice(),
+            ChunkChecksum::Sha256(hasher, expected) => hasher.finalize().as_slice() == expected.as_slice(),
+        }
+    }
+}
+
+fn part_prefix(upload_id: &str) -> String {
+    format!("tus/{}/", upload_id)
+}
+
+/// Unique per request, two PATCHes racing for the same offset never write to the same object. Only the
+/// part recorded by `advance_tus_offset` is used, the loser removes its own.
+fn part_key(upload_id: &str, offset: i64) -> String {
+    format!("{}{:020}-{}", part_prefix(upload_id), offset, uuid::Uuid::new_v4().simple())
+}
+
+fn env_u64(name: &str, default: u64) -> u64 {
+    dotenv().ok();
+    env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Uploads that did not move for `TUS_UPLOAD_EXPIRY_SECONDS` (defaults to 1 day) are removed with their parts.
+fn upload_expiry() -> chrono::Duration {
+    chrono::Duration::seconds(env_u64("TUS_UPLOAD_EXPIRY_SECONDS", 24 * 60 * 60).clamp(1, i64::MAX as u64) as i64)
+}
+
+/// Seconds between expiry runs, read from `TUS_PRUNE_INTERVAL_SECONDS` (defaults to 1 hour).
+fn prune_interval() -> Duration {
+    Duration::from_secs(env_u64("TUS_PRUNE_INTERVAL_SECONDS", 60 * 60).max(1))
+}
+
+/// Parses `Upload-Metadata`: comma separated `key base64(value)` pairs, the value may be omitted.
+pub fn parse_upload_metadata(header: &str) -> Result<HashMap<String, String>, TusError> {
+    let mut metadata = HashMap::new();
+    for pair in header.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+        let (key, value) = match pair.split_once(' ') {
+            Some((key, encoded)) => {
+                let decoded = STANDARD.decode(encoded.trim())
+                    .ok()
+                    .and_then(|bytes| String::from_utf8(bytes).ok())
+                    .ok_or_else(|| TusError::new("Upload-Metadata values must be base64 encoded UTF-8", StatusCode::BAD_REQUEST))?;
+                (key, decoded)
+            }
+            None => (pair, String::new()),
+        };
+        metadata.insert(key.to_string(), value);
+    }
+    Ok(metadata)
+}
+
+pub async fn create_upload(owner: &AuthUser, upload_length: i64, metadata: HashMap<String, String>) -> Result<TusUpload, TusError> {
+    if upload_length < 0 {
+        return Err(TusError::new("Upload-Length must not be negative", StatusCode::BAD_REQUEST))
+    }
+    if upload_length as u64 > max_upload_size() {
+        return Err(TusError::new("Upload-Length exceeds Tus-Max-Size", StatusCode::PAYLOAD_TOO_LARGE))
+    }
+
+    let file_name = metadata.get("filename")
+        .and_then(|name| sanitize_file_name(name))
+        .ok_or_else(|| TusError::new("Upload-Metadata must contain a usable filename", StatusCode::BAD_REQUEST))?;
+    // Same values as the `on_conflict` query parameter of multipart uploads, applied once the upload is complete.
+    let on_conflict = match metadata.get("on_conflict") {
+        Some(value) => ConflictPolicy::parse(value)
+            .ok_or_else(|| TusError::new("on_conflict must be reject, rename or version", StatusCode::BAD_REQUEST))?,
+        None => ConflictPolicy::default(),
+    };
+    // Same as the `folder_id` query parameter of multipart uploads, absent for the top level.
+    let folder_id = match metadata.get("folder_id") {
+        Some(value) => Some(value.trim().parse::<i32>()
+            .map_err(|_| TusError::new("folder_id must be a folder id", StatusCode::BAD_REQUEST))?),
+        None => None,
+    };
+    if let Some(folder_id) = folder_id {
+        load_owned_folder(folder_id, owner).await?;
+    }
+    if on_conflict == ConflictPolicy::Reject {
+        check_file_name_available(owner.id, folder_id, &file_name).await?;
+    }
+
+    let new_upload = TusUploadToInsert {
+        upload_id: uuid::Uuid::new_v4().simple().to_string(),
+        file_name,
+        content_type: metadata.get("filetype").cloned(),
+        upload_length,
+        owner_id: Some(owner.id),
+        on_conflict: on_conflict.as_str().to_string(),
+        folder_id,
+    };
+    Ok(create_tus_upload(new_upload).await?)
+}
+
+/// Uploads of other users are reported as missing so their ids cannot be probed.
+pub async fn upload_status(owner: &AuthUser, upload_id: String) -> Result<TusUpload, TusError> {
+    get_tus_upload(upload_id).await?
+        .filter(|upload| upload.owner_id == Some(owner.id))
+        .ok_or_else(|| TusError::new("Upload not found", StatusCode::NOT_FOUND))
+}
+
+/// Stores one PATCH body as its own part object. A request that breaks off midway stores nothing,
+/// the client then resumes from the last acknowledged offset.
+pub async fn append_chunk(owner: &AuthUser, upload_id: String, offset: i64, checksum: Option<&str>, body: Body) -> Result<TusPatchResult, TusError> {
+    let upload = upload_status(owner, upload_id.clone()).await?;
+    if offset != upload.upload_offset {
+        return Err(TusError::new("Upload-Offset does not match the current offset", StatusCode::CONFLICT))
+    }
+    let mut checksum = checksum.map(ChunkChecksum::parse).transpose()?;
+
+    let remaining = (upload.upload_length - upload.upload_offset) as u64;
+    let mut received: u64 = 0;
+    let chunks = body.into_data_stream().map(|chunk| {
+        let chunk = chunk.map_err(|e| StorageError::Backend(e.to_string()))?;
+        received += chunk.len() as u64;
+        if received > remaining {
+            return Err(StorageError::TooLarge(remaining))
+        }
+        if let Some(checksum) = checksum.as_mut() {
+            checksum.update(&chunk);
+        }
+        Ok(chunk)
+    });
+
+    let storage = storage_backend().await;
+    let key = part_key(&upload_id, offset);
+    let written = storage.put_stream(&key, Box::pin(chunks)).await?;
+
+    if let Some(checksum) = checksum && !checksum.matches() {
+        storage.delete(&key).await?;
+        return Err(TusError::new("Upload-Checksum does not match", StatusCode::from_u16(CHECKSUM_MISMATCH).unwrap()))
+    }
+
+    let new_offset = offset + written as i64;
+    if written == 0 {
+        // Nothing to keep, but an empty PATCH still completes a zero length upload.
+        storage.delete(&key).await?;
+    } else if !advance_tus_offset(TusUploadPartToInsert { upload_id: upload_id.clone(), part_offset: offset, part_key: key.clone(), size: written as i64 }).await? {
+        storage.delete(&key).await?;
+        return Err(TusError::new("Upload-Offset does not match the current offset", StatusCode::CONFLICT))
+    }
+
+    let file_link = if new_offset == upload.upload_length {
+        Some(finish_upload(owner, upload).await?)
+    } else {
+        None
+    };
+    Ok(TusPatchResult { upload_offset: new_offset, file_link })
+}
+
+/// Concatenates all parts into the final object, registers the file and drops the upload state.
+async fn finish_upload(owner: &AuthUser, upload: TusUpload) -> Result<String, TusError> {
+    let storage = storage_backend().await;
+    let parts = list_tus_upload_parts(upload.upload_id.clone()).await?;
+
+    let part_storage = storage.clone();
+    let assembled = stream::iter(parts.into_iter().map(|part| part.part_key))
+        .then(move |key| {
+            let storage = part_storage.clone();
+            async move { storage.get_stream(&key, None).await }
+        })
+        .try_flatten();
+
+    let content_type = upload_content_type(upload.content_type.as_deref(), &upload.file_name);
+    let stored_blob = store_content(Box::pin(assembled)).await?;
+    if stored_blob.size != upload.upload_length {
+        discard_blob(stored_blob.storage_path).await?;
+        return Err(TusError::new("Assembled upload does not match Upload-Length", StatusCode::INTERNAL_SERVER_ERROR))
+    }
+    // The folder may have been deleted while the upload was running.
+    if let Some(folder_id) = upload.folder_id
+        && let Err(error) = load_owned_folder(folder_id, owner).await {
+        discard_blob(stored_blob.storage_path).await?;
+        return Err(error.into())
+    }
+
+    let on_conflict = ConflictPolicy::parse(&upload.on_conflict).unwrap_or_default();
+    let link = register_file(upload.file_name.clone(), content_type, stored_blob, owner.id, upload.folder_id, on_conflict).await?;
+    remove_upload(&upload.upload_id).await?;
+    Ok(link)
+}
+
+/// Removes everything under the upload's prefix, including parts of requests that broke off before they were recorded.
+async fn remove_upload(upload_id: &str) -> Result<(), TusError> {
+    let storage = storage_backend().await;
+    for part in storage.list(&part_prefix(upload_id)).await? {
+        storage.delete(&part.key).await?;
+    }
+    delete_tus_upload(upload_id.to_string()).await?;
+    Ok(())
+}
+
+pub async fn terminate_upload(owner: &AuthUser, upload_id: String) -> Result<(), TusError> {
+    let upload = upload_status(owner, upload_id).await?;
+    remove_upload(&upload.upload_id).await
+}
+
+/// Removes the uploads that expired, returns how many.
+pub async fn prune_stale_uploads() -> Result<usize, TusError> {
+    let stale = list_stale_tus_uploads(Utc::now().naive_utc() - upload_expiry()).await?;
+    for upload_id in &stale {
+        remove_upload(upload_id).await?;
+    }
+    Ok(stale.len())
+}
+
+pub fn spawn_tus_upload_pruner() {
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(prune_interval());
+        loop {
+            ticks.tick().await;
+            match prune_stale_uploads().await {
+                Ok(0) => {}
+                Ok(removed) => println!("Removed {} expired uploads", removed),
+                Err(error) => println!("Upload pruning failed: {}", error),
+            }
+        }
+    });
+}
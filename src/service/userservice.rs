@@ -1,26 +1,62 @@
-use crate::model::usermodel::{ConversionError, CreateUserRequest, LoginRequest, User};
-use crate::repository::userrepository::{check_if_user_exist_login, create_user as other_create_user};
+use tokio::task;
+use crate::model::usermodel::{ConversionError, CreateUserRequest, User};
+use crate::model::usermodel::ConversionError::*;
+use crate::repository::userrepository::{create_user as other_create_user, get_user_by_identifier, update_user_password};
+use crate::service::accountservice::send_verification_email;
+use crate::Security::password::{hash_password, verify_dummy_password, verify_password};
+
+pub async fn create_user(mut user: CreateUserRequest) -> Result<bool, ConversionError>{
+    // Logins treat identifiers with an `@` as email addresses, such a name could never log in.
+    if user.name.trim().is_empty() || user.name.contains('@') {
+        return Err(BadRequest("name must not be empty or contain @".to_string()))
+    }
+    let password = user.password.clone();
+    // Argon2 is deliberately expensive, keep it off the async workers.
+    user.password = task::spawn_blocking(move || hash_password(&password)).await??;
 
-pub async fn create_user(user: CreateUserRequest) -> bool{
-    
     let created_user = other_create_user(user).await;
     match created_user {
-        Ok(_) => {
-            true
+        Ok(Some(created)) => {
+            // A failed mail does not undo the signup, a password reset verifies the address as well.
+            if let Err(error) = send_verification_email(&created).await {
+                println!("Verification mail failed: {}", error);
+            }
+            Ok(true)
+        }
+        Ok(None) => {
+            Ok(false)
         }
         Err(_) => {
-            false
+            Ok(false)
         }
     }
 }
-pub async fn check_user_login(user: LoginRequest) -> Result<bool, ConversionError>{
-    if check_if_user_exist_login(user).await?{
-        Ok(true)
-    }
-    else { 
-        Ok(false)
-    }
+
+/// The user a login identifier refers to, by email when it contains an `@` and by name otherwise.
+pub async fn find_login_user(identifier: String) -> Result<Option<User>, ConversionError> {
+    get_user_by_identifier(identifier).await
 }
 
+/// Returns `stored_user` when the password matches it. Hashes from before Argon2id, or with outdated
+/// cost parameters, are replaced on a successful login.
+pub async fn check_user_login(stored_user: Option<User>, password: String) -> Result<Option<User>, ConversionError>{
+    let Some(stored_user) = stored_user else {
+        task::spawn_blocking(move || verify_dummy_password(&password)).await?;
+        return Ok(None)
+    };
 
+    let entered_password = password.clone();
+    let stored_password = stored_user.password.clone();
+    let check = task::spawn_blocking(move || verify_password(&entered_password, &stored_password)).await?;
+    if !check.valid {
+        return Ok(None)
+    }
 
+    if check.needs_rehash && let Some(user_id) = stored_user.id {
+        match task::spawn_blocking(move || hash_password(&password)).await? {
+            Ok(password_hash) => update_user_password(user_id, password_hash).await?,
+            Err(error) => println!("Rehash on login failed: {}", error),
+        }
+    }
+    Ok(Some(stored_user))
+}
@@ -0,0 +1,117 @@
+use std::env;
+use std::time::Duration;
+use chrono::Utc;
+use dotenv::dotenv;
+use crate::model::filemodel::{FileResponse, GetFileResponse};
+use crate::model::securitymodel::AuthUser;
+use crate::model::usermodel::{ConversionError, File};
+use crate::model::usermodel::ConversionError::*;
+use crate::model::versionmodel::{FileVersionResponse, VersionRetention};
+use crate::repository::filerepository::get_blob_by_path;
+use crate::repository::versionrepository::{get_file_version, list_file_versions, prune_file_versions, revert_to_version};
+use crate::service::fileservice::{delete_stored_object, load_owned_file};
+
+/// Read without the positive filter of the other settings, 0 switches a retention rule off.
+fn env_u64(name: &str, default: u64) -> u64 {
+    dotenv().ok();
+    env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Read from `VERSION_KEEP_LAST` (replaced versions kept per file, defaults to 10) and `VERSION_KEEP_DAYS`
+/// (defaults to 30). Setting either to 0 disables that rule.
+fn version_retention() -> VersionRetention {
+    let keep_last = env_u64("VERSION_KEEP_LAST", 10);
+    let keep_days = env_u64("VERSION_KEEP_DAYS", 30);
+    VersionRetention {
+        keep_last: (keep_last > 0).then_some(keep_last as i64),
+        replaced_before: (keep_days > 0).then(|| Utc::now().naive_utc() - chrono::Duration::days(keep_days as i64)),
+    }
+}
+
+/// Seconds between pruner runs, read from `VERSION_PRUNE_INTERVAL_SECONDS` (defaults to 1 hour).
+fn prune_interval() -> Duration {
+    Duration::from_secs(env_u64("VERSION_PRUNE_INTERVAL_SECONDS", 60 * 60).max(1))
+}
+
+/// Versions belong to live files, a file in the trash has to be restored first.
+async fn load_versioned_file(owner: &AuthUser, file_id: i32) -> Result<File, ConversionError> {
+    let owned_file = load_owned_file(file_id, owner).await?;
+    if owned_file.is_deleted == Some(1) {
+        return Err(NotFound(format!("File {}", file_id)))
+    }
+    Ok(owned_file)
+}
+
+/// The current version first, then the replaced ones from newest to oldest.
+pub async fn list_versions(owner: &AuthUser, file_id: i32) -> Result<Vec<FileVersionResponse>, ConversionError> {
+    let current = load_versioned_file(owner, file_id).await?;
+    let mut versions = vec![FileVersionResponse::from(&current)];
+    versions.extend(list_file_versions(file_id).await?.into_iter().map(FileVersionResponse::from));
+    Ok(versions)
+}
+
+/// What a download of `version` serves. Old versions go out under the current name.
+pub async fn load_version_content(owner: &AuthUser, file_id: i32, version: i32) -> Result<GetFileResponse, ConversionError> {
+    let current = load_versioned_file(owner, file_id).await?;
+    if version == current.version {
+        let stored_blob = get_blob_by_path(current.storage_path.clone()).await?;
+        return Ok(GetFileResponse {
+            filename: current.file_name,
+            filepath: current.storage_path,
+            content_type: current.content_type,
+            content_hash: current.content_hash,
+            hash_algorithm: stored_blob.map(|stored_blob| stored_blob.hash_algorithm),
+            last_modified: current.updated_at.or(current.created_at),
+            share: None
+        })
+    }
+
+    let archived = get_file_version(file_id, version).await?
+        .ok_or_else(|| NotFound(format!("Version {} of File {}", version, file_id)))?;
+    let stored_blob = get_blob_by_path(archived.storage_path.clone()).await?;
+    Ok(GetFileResponse {
+        filename: current.file_name,
+        filepath: archived.storage_path,
+        content_type: archived.content_type,
+        content_hash: archived.content_hash,
+        hash_algorithm: stored_blob.map(|stored_blob| stored_blob.hash_algorithm),
+        last_modified: None,
+        share: None
+    })
+}
+
+/// Makes the content of `version` current again under a new version number.
+pub async fn revert_file(owner: &AuthUser, file_id: i32, version: i32) -> Result<FileResponse, ConversionError> {
+    let current = load_versioned_file(owner, file_id).await?;
+    if version == current.version {
+        return Err(BadRequest(format!("Version {} is already the current one", version)))
+    }
+    let reverted = revert_to_version(file_id, version).await?
+        .ok_or_else(|| NotFound(format!("Version {} of File {}", version, file_id)))?;
+    Ok(FileResponse::from(reverted))
+}
+
+/// Applies the retention rules once and deletes the objects no file or version uses anymore.
+pub async fn prune_versions() -> Result<usize, ConversionError> {
+    let unreferenced = prune_file_versions(version_retention()).await?;
+    for path in &unreferenced {
+        delete_stored_object(path).await?;
+    }
+    Ok(unreferenced.len())
+}
+
+/// Runs `prune_versions` right away and then on every interval. A failed run is logged and retried next time.
+pub fn spawn_version_pruner() {
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(prune_interval());
+        loop {
+            ticks.tick().await;
+            if let Err(error) = prune_versions().await {
+                println!("Version pruning failed: {}", error);
+            }
+        }
+    });
+}
@@ -0,0 +1,170 @@
+use std::io::{ErrorKind, SeekFrom};
+use std::path::PathBuf;
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio_util::io::ReaderStream;
+use crate::model::storagemodel::{ByteRange, ObjectMeta, StorageError};
+use crate::storage::storagebackend::{validate_key, ByteStream, StorageBackend};
+
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        LocalStorage { root: root.into() }
+    }
+
+    fn path_for(&self, key: &str) -> Result<PathBuf, StorageError> {
+        validate_key(key)?;
+        Ok(self.root.join(key))
+    }
+}
+
+fn map_not_found(key: &str, err: std::io::Error) -> StorageError {
+    if err.kind() == ErrorKind::NotFound {
+        StorageError::NotFound(key.to_string())
+    } else {
+        StorageError::from(err)
+    }
+}
+
+fn meta_from_fs(key: String, metadata: &std::fs::Metadata) -> ObjectMeta {
+    let last_modified = metadata.modified().ok().map(|time| DateTime::<Utc>::from(time).naive_utc());
+    ObjectMeta {
+        key,
+        size: metadata.len(),
+        last_modified,
+    }
+}
+
+#[async_trait]
+impl StorageBackend for LocalStorage {
+    async fn put_stream(&self, key: &str, mut data: ByteStream<'_>) -> Result<u64, StorageError> {
+        let path = self.path_for(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        // Write next to the target and rename at the end so a failed upload never replaces an existing object.
+        let partial_path = PathBuf::from(format!("{}.part", path.display()));
+        let mut partial_file = tokio::fs::File::create(&partial_path).await?;
+        let mut written: u64 = 0;
+
+        let result: Result<(), StorageError> = async {
+            while let Some(chunk) = data.next().await {
+                let chunk = chunk?;
+                partial_file.write_all(&chunk).await?;
+                written += chunk.len() as u64;
+            }
+            partial_file.flush().await?;
+            Ok(())
+        }.await;
+
+        drop(partial_file);
+        if let Err(error) = result {
+            let _ = tokio::fs::remove_file(&partial_path).await;
+            return Err(error)
+        }
+        tokio::fs::rename(&partial_path, &path).await?;
+        Ok(written)
+    }
+
+    async fn get(&self, key: &str) -> Result<Bytes, StorageError> {
+        let path = self.path_for(key)?;
+        let data = tokio::fs::read(&path).await.map_err(|e| map_not_found(key, e))?;
+        Ok(Bytes::from(data))
+    }
+
+    async fn get_stream(&self, key: &str, range: Option<ByteRange>) -> Result<ByteStream<'static>, StorageError> {
+        let path = self.path_for(key)?;
+        let mut file = tokio::fs::File::open(&path).await.map_err(|e| map_not_found(key, e))?;
+
+        let stream: ByteStream<'static> = match range {
+            Some(range) => {
+                file.seek(SeekFrom::Start(range.start)).await?;
+                Box::pin(ReaderStream::with_capacity(file.take(range.length()), READ_CHUNK_SIZE).map(|chunk| chunk.map_err(StorageError::from)))
+            }
+            None => Box::pin(ReaderStream::with_capacity(file, READ_CHUNK_SIZE).map(|chunk| chunk.map_err(StorageError::from))),
+        };
+        Ok(stream)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        let path = self.path_for(key)?;
+        tokio::fs::remove_file(&path).await.map_err(|e| map_not_found(key, e))?;
+
+        // Clean up directories the key left empty, remove_dir refuses as soon as one still has entries.
+        let mut parent = path.parent();
+        while let Some(dir) = parent {
+            if dir == self.root || tokio::fs::remove_dir(dir).await.is_err() {
+                break
+            }
+            parent = dir.parent();
+        }
+        Ok(())
+    }
+
+    async fn head(&self, key: &str) -> Result<ObjectMeta, StorageError> {
+        let path = self.path_for(key)?;
+        let metadata = tokio::fs::metadata(&path).await.map_err(|e| map_not_found(key, e))?;
+        Ok(meta_from_fs(key.to_string(), &metadata))
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectMeta>, StorageError> {
+        // Keys map to paths, so only the directory the prefix points into has to be walked.
+        let directory = prefix.rsplit_once('/').map_or("", |(directory, _)| directory);
+        if !directory.is_empty() {
+            validate_key(directory)?;
+        }
+        let mut objects = Vec::new();
+        let mut pending = vec![(self.root.join(directory), directory.to_string())];
+
+        while let Some((dir, relative)) = pending.pop() {
+            let mut entries = match tokio::fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(StorageError::from(e)),
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let key = if relative.is_empty() { name } else { format!("{}/{}", relative, name) };
+                let metadata = entry.metadata().await?;
+                if metadata.is_dir() {
+                    pending.push((entry.path(), key));
+                } else if key.starts_with(prefix) {
+                    objects.push(meta_from_fs(key, &metadata));
+                }
+            }
+        }
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(objects)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn lists_by_prefix_within_its_directory() {
+        let root = std::env::temp_dir().join(format!("fileshare-storage-{}", uuid::Uuid::new_v4().simple()));
+        let storage = LocalStorage::new(&root);
+        for key in ["tus/a/1", "tus/a/2", "tus/ab/1", "blobs/x"] {
+            storage.put(key, Bytes::from_static(b"data")).await.unwrap();
+        }
+
+        let keys = |objects: Vec<ObjectMeta>| objects.into_iter().map(|object| object.key).collect::<Vec<_>>();
+        assert_eq!(keys(storage.list("tus/a/").await.unwrap()), vec!["tus/a/1", "tus/a/2"]);
+        assert_eq!(keys(storage.list("tus/a").await.unwrap()), vec!["tus/a/1", "tus/a/2", "tus/ab/1"]);
+        assert_eq!(keys(storage.list("").await.unwrap()), vec!["blobs/x", "tus/a/1", "tus/a/2", "tus/ab/1"]);
+        assert!(storage.list("missing/").await.unwrap().is_empty());
+        assert!(matches!(storage.list("../").await, Err(StorageError::InvalidKey(_))));
+        tokio::fs::remove_dir_all(&root).await.unwrap();
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+use async_trait::async_trait;
+use bytes::{Bytes, BytesMut};
+use chrono::{NaiveDateTime, Utc};
+use futures_util::{stream, StreamExt};
+use crate::model::storagemodel::{ByteRange, ObjectMeta, StorageError};
+use crate::storage::storagebackend::{validate_key, ByteStream, StorageBackend};
+
+/// Keeps every object in process memory. Meant for tests and local development only.
+#[derive(Default)]
+pub struct MemoryStorage {
+    objects: RwLock<BTreeMap<String, (Bytes, NaiveDateTime)>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        MemoryStorage::default()
+    }
+}
+
+fn poisoned<T>(_: T) -> StorageError {
+    StorageError::Backend("Memory storage lock poisoned".to_string())
+}
+
+#[async_trait]
+impl StorageBackend for MemoryStorage {
+    async fn put_stream(&self, key: &str, mut data: ByteStream<'_>) -> Result<u64, StorageError> {
+        validate_key(key)?;
+        let mut buffer = BytesMut::new();
+        while let Some(chunk) = data.next().await {
+            buffer.extend_from_slice(&chunk?);
+        }
+        let written = buffer.len() as u64;
+        let mut objects = self.objects.write().map_err(poisoned)?;
+        objects.insert(key.to_string(), (buffer.freeze(), Utc::now().naive_utc()));
+        Ok(written)
+    }
+
+    async fn get(&self, key: &str) -> Result<Bytes, StorageError> {
+        let objects = self.objects.read().map_err(poisoned)?;
+        objects.get(key)
+            .map(|(data, _)| data.clone())
+            .ok_or_else(|| StorageError::NotFound(key.to_string()))
+    }
+
+    async fn get_stream(&self, key: &str, range: Option<ByteRange>) -> Result<ByteStream<'static>, StorageError> {
+        let data = self.get(key).await?;
+        let data = match range {
+            Some(range) => data.slice(range.start as usize..=range.end as usize),
+            None => data,
+        };
+        Ok(Box::pin(stream::once(async { Ok(data) })))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        let mut objects = self.objects.write().map_err(poisoned)?;
+        objects.remove(key)
+            .map(|_| ())
+            .ok_or_else(|| StorageError::NotFound(key.to_string()))
+    }
+
+    async fn head(&self, key: &str) -> Result<ObjectMeta, StorageError> {
+        let objects = self.objects.read().map_err(poisoned)?;
+        objects.get(key)
+            .map(|(data, modified)| ObjectMeta {
+                key: key.to_string(),
+                size: data.len() as u64,
+                last_modified: Some(*modified),
+            })
+            .ok_or_else(|| StorageError::NotFound(key.to_string()))
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectMeta>, StorageError> {
+        let objects = self.objects.read().map_err(poisoned)?;
+        Ok(objects.iter()
+            .filter(|(key, _)| key.starts_with(prefix))
+            .map(|(key, (data, modified))| ObjectMeta {
+                key: key.clone(),
+                size: data.len() as u64,
+                last_modified: Some(*modified),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::TryStreamExt;
+
+    async fn read_all(storage: &MemoryStorage, key: &str, range: Option<ByteRange>) -> Vec<u8> {
+        let chunks: Vec<Bytes> = storage.get_stream(key, range).await.unwrap().try_collect().await.unwrap();
+        chunks.concat()
+    }
+
+    #[tokio::test]
+    async fn stores_and_reads_back_a_stream() {
+        let storage = MemoryStorage::new();
+        let chunks = stream::iter(vec![Ok(Bytes::from_static(b"hello ")), Ok(Bytes::from_static(b"world"))]);
+
+        assert_eq!(storage.put_stream("blobs/a", Box::pin(chunks)).await.unwrap(), 11);
+        assert_eq!(storage.get("blobs/a").await.unwrap(), Bytes::from_static(b"hello world"));
+        assert_eq!(read_all(&storage, "blobs/a", None).await, b"hello world");
+        assert_eq!(storage.head("blobs/a").await.unwrap().size, 11);
+    }
+
+    #[tokio::test]
+    async fn streams_an_inclusive_range() {
+        let storage = MemoryStorage::new();
+        storage.put("blobs/a", Bytes::from_static(b"0123456789")).await.unwrap();
+
+        assert_eq!(read_all(&storage, "blobs/a", Some(ByteRange { start: 2, end: 4 })).await, b"234");
+        assert_eq!(read_all(&storage, "blobs/a", Some(ByteRange { start: 9, end: 9 })).await, b"9");
+    }
+
+    #[tokio::test]
+    async fn reports_missing_objects() {
+        let storage = MemoryStorage::new();
+
+        assert!(matches!(storage.get("blobs/missing").await, Err(StorageError::NotFound(_))));
+        assert!(matches!(storage.get_stream("blobs/missing", None).await, Err(StorageError::NotFound(_))));
+        assert!(matches!(storage.head("blobs/missing").await, Err(StorageError::NotFound(_))));
+        assert!(matches!(storage.delete("blobs/missing").await, Err(StorageError::NotFound(_))));
+
+        storage.put("blobs/a", Bytes::from_static(b"a")).await.unwrap();
+        storage.delete("blobs/a").await.unwrap();
+        assert!(matches!(storage.get("blobs/a").await, Err(StorageError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn lists_by_prefix_in_key_order() {
+        let storage = MemoryStorage::new();
+        for key in ["tus/1/00000000000000000005", "tus/1/00000000000000000000", "tus/2/00000000000000000000", "blobs/a"] {
+            storage.put(key, Bytes::from_static(b"x")).await.unwrap();
+        }
+
+        let keys: Vec<String> = storage.list("tus/1/").await.unwrap().into_iter().map(|meta| meta.key).collect();
+        assert_eq!(keys, vec!["tus/1/00000000000000000000", "tus/1/00000000000000000005"]);
+        assert!(storage.list("nothing/").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn keeps_nothing_from_a_failed_stream_or_an_invalid_key() {
+        let storage = MemoryStorage::new();
+        let chunks = stream::iter(vec![Ok(Bytes::from_static(b"part")), Err(StorageError::TooLarge(4))]);
+
+        assert!(matches!(storage.put_stream("blobs/a", Box::pin(chunks)).await, Err(StorageError::TooLarge(4))));
+        assert!(matches!(storage.head("blobs/a").await, Err(StorageError::NotFound(_))));
+        assert!(matches!(storage.put("../escape", Bytes::from_static(b"x")).await, Err(StorageError::InvalidKey(_))));
+    }
+}
@@ -0,0 +1,224 @@
+use async_trait::async_trait;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
+use bytes::{Bytes, BytesMut};
+use chrono::{DateTime, NaiveDateTime};
+use futures_util::StreamExt;
+use tokio_util::io::ReaderStream;
+use crate::model::storagemodel::{ByteRange, ObjectMeta, StorageError};
+use crate::storage::storagebackend::{self, validate_key, StorageBackend};
+
+/// S3 requires every part except the last one to be at least 5 MiB.
+const MULTIPART_PART_SIZE: usize = 8 * 1024 * 1024;
+
+pub struct S3Storage {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+}
+
+impl S3Storage {
+    pub async fn new(bucket: String) -> Self {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+        S3Storage {
+            client: aws_sdk_s3::Client::new(&config),
+            bucket,
+        }
+    }
+
+    async fn upload_part(&self, key: &str, upload_id: &str, part_number: i32, data: Bytes) -> Result<CompletedPart, StorageError> {
+        let output = self.client.upload_part()
+            .bucket(&self.bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .part_number(part_number)
+            .body(ByteStream::from(data))
+            .send()
+            .await
+            .map_err(backend_error)?;
+        Ok(CompletedPart::builder()
+            .set_e_tag(output.e_tag().map(str::to_string))
+            .part_number(part_number)
+            .build())
+    }
+
+    async fn upload_parts(&self, key: &str, upload_id: &str, mut data: storagebackend::ByteStream<'_>) -> Result<(u64, Vec<CompletedPart>), StorageError> {
+        let mut parts = Vec::new();
+        let mut buffer = BytesMut::with_capacity(MULTIPART_PART_SIZE);
+        let mut written: u64 = 0;
+
+        while let Some(chunk) = data.next().await {
+            let chunk = chunk?;
+            written += chunk.len() as u64;
+            buffer.extend_from_slice(&chunk);
+            if buffer.len() >= MULTIPART_PART_SIZE {
+                let part_number = parts.len() as i32 + 1;
+                parts.push(self.upload_part(key, upload_id, part_number, buffer.split().freeze()).await?);
+            }
+        }
+        if !buffer.is_empty() || parts.is_empty() {
+            let part_number = parts.len() as i32 + 1;
+            parts.push(self.upload_part(key, upload_id, part_number, buffer.freeze()).await?);
+        }
+        Ok((written, parts))
+    }
+
+    /// Drops the parts of an upload that will not be completed, S3 keeps and bills them otherwise.
+    /// Best effort, the original error is what the caller reports.
+    async fn abort_upload(&self, key: &str, upload_id: &str) {
+        let _ = self.client.abort_multipart_upload()
+            .bucket(&self.bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .send()
+            .await;
+    }
+}
+
+fn to_naive(time: Option<&aws_sdk_s3::primitives::DateTime>) -> Option<NaiveDateTime> {
+    time.and_then(|t| DateTime::from_timestamp(t.secs(), t.subsec_nanos()))
+        .map(|t| t.naive_utc())
+}
+
+fn backend_error(err: impl std::error::Error) -> StorageError {
+    StorageError::Backend(err.to_string())
+}
+
+#[async_trait]
+impl StorageBackend for S3Storage {
+    async fn put_stream(&self, key: &str, data: storagebackend::ByteStream<'_>) -> Result<u64, StorageError> {
+        validate_key(key)?;
+        let upload = self.client.create_multipart_upload()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(backend_error)?;
+        let upload_id = upload.upload_id()
+            .ok_or_else(|| StorageError::Backend("S3 did not return an upload id".to_string()))?
+            .to_string();
+
+        let (written, parts) = match self.upload_parts(key, &upload_id, data).await {
+            Ok(result) => result,
+            Err(error) => {
+                self.abort_upload(key, &upload_id).await;
+                return Err(error)
+            }
+        };
+
+        let completed = self.client.complete_multipart_upload()
+            .bucket(&self.bucket)
+            .key(key)
+            .upload_id(&upload_id)
+            .multipart_upload(CompletedMultipartUpload::builder().set_parts(Some(parts)).build())
+            .send()
+            .await;
+        if let Err(error) = completed {
+            self.abort_upload(key, &upload_id).await;
+            return Err(backend_error(error))
+        }
+        Ok(written)
+    }
+
+    async fn put(&self, key: &str, data: Bytes) -> Result<(), StorageError> {
+        validate_key(key)?;
+        self.client.put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .body(ByteStream::from(data))
+            .send()
+            .await
+            .map_err(backend_error)?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Bytes, StorageError> {
+        validate_key(key)?;
+        let output = self.client.get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| match e.as_service_error() {
+                Some(service_error) if service_error.is_no_such_key() => StorageError::NotFound(key.to_string()),
+                _ => backend_error(e),
+            })?;
+        let data = output.body.collect().await.map_err(backend_error)?;
+        Ok(data.into_bytes())
+    }
+
+    async fn get_stream(&self, key: &str, range: Option<ByteRange>) -> Result<storagebackend::ByteStream<'static>, StorageError> {
+        validate_key(key)?;
+        let output = self.client.get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .set_range(range.map(|range| format!("bytes={}-{}", range.start, range.end)))
+            .send()
+            .await
+            .map_err(|e| match e.as_service_error() {
+                Some(service_error) if service_error.is_no_such_key() => StorageError::NotFound(key.to_string()),
+                _ => backend_error(e),
+            })?;
+        let reader = output.body.into_async_read();
+        Ok(Box::pin(ReaderStream::new(reader).map(|chunk| chunk.map_err(StorageError::from))))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        validate_key(key)?;
+        self.client.delete_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(backend_error)?;
+        Ok(())
+    }
+
+    async fn head(&self, key: &str) -> Result<ObjectMeta, StorageError> {
+        validate_key(key)?;
+        let output = self.client.head_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| match e.as_service_error() {
+                Some(service_error) if service_error.is_not_found() => StorageError::NotFound(key.to_string()),
+                _ => backend_error(e),
+            })?;
+        Ok(ObjectMeta {
+            key: key.to_string(),
+            size: output.content_length().unwrap_or(0).max(0) as u64,
+            last_modified: to_naive(output.last_modified()),
+        })
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectMeta>, StorageError> {
+        let mut objects = Vec::new();
+        let mut continuation_token: Option<String> = None;
+
+        loop {
+            let output = self.client.list_objects_v2()
+                .bucket(&self.bucket)
+                .prefix(prefix)
+                .set_continuation_token(continuation_token.take())
+                .send()
+                .await
+                .map_err(backend_error)?;
+
+            for object in output.contents() {
+                if let Some(key) = object.key() {
+                    objects.push(ObjectMeta {
+                        key: key.to_string(),
+                        size: object.size().unwrap_or(0).max(0) as u64,
+                        last_modified: to_naive(object.last_modified()),
+                    });
+                }
+            }
+
+            match output.next_continuation_token() {
+                Some(token) if output.is_truncated().unwrap_or(false) => continuation_token = Some(token.to_string()),
+                _ => break,
+            }
+        }
+        Ok(objects)
+    }
+}
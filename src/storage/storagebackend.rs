@@ -0,0 +1,63 @@
+use std::env;
+use std::sync::Arc;
+use async_trait::async_trait;
+use bytes::Bytes;
+use dotenv::dotenv;
+use futures_util::stream::{self, BoxStream};
+use tokio::sync::OnceCell;
+use crate::model::storagemodel::{ByteRange, ObjectMeta, StorageError};
+use crate::storage::localstorage::LocalStorage;
+use crate::storage::memorystorage::MemoryStorage;
+use crate::storage::s3storage::S3Storage;
+
+pub type ByteStream<'a> = BoxStream<'a, Result<Bytes, StorageError>>;
+
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    /// Writes the stream chunk by chunk and returns the number of bytes stored.
+    /// If the stream yields an error, nothing is left behind under `key`.
+    async fn put_stream(&self, key: &str, data: ByteStream<'_>) -> Result<u64, StorageError>;
+    async fn put(&self, key: &str, data: Bytes) -> Result<(), StorageError> {
+        self.put_stream(key, Box::pin(stream::once(async { Ok(data) }))).await?;
+        Ok(())
+    }
+    async fn get(&self, key: &str) -> Result<Bytes, StorageError>;
+    /// Opens the object for streaming, optionally limited to `range`. The caller has to make sure
+    /// the range lies within the object.
+    async fn get_stream(&self, key: &str, range: Option<ByteRange>) -> Result<ByteStream<'static>, StorageError>;
+    async fn delete(&self, key: &str) -> Result<(), StorageError>;
+    async fn head(&self, key: &str) -> Result<ObjectMeta, StorageError>;
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectMeta>, StorageError>;
+}
+
+static STORAGE: OnceCell<Arc<dyn StorageBackend>> = OnceCell::const_new();
+
+/// Returns the process wide storage backend, selected once via `STORAGE_BACKEND`
+/// (`local`, `s3` or `memory`, defaults to `local`).
+pub async fn storage_backend() -> Arc<dyn StorageBackend> {
+    STORAGE.get_or_init(|| async {
+        dotenv().ok();
+        let kind = env::var("STORAGE_BACKEND").unwrap_or_else(|_| "local".to_string());
+
+        let backend: Arc<dyn StorageBackend> = match kind.to_lowercase().as_str() {
+            "s3" => {
+                let bucket = env::var("S3_BUCKET").unwrap_or_else(|_| "fileshareapistorage".to_string());
+                Arc::new(S3Storage::new(bucket).await)
+            }
+            "memory" => Arc::new(MemoryStorage::new()),
+            _ => {
+                let root = env::var("STORAGE_PATH").unwrap_or_else(|_| "content".to_string());
+                Arc::new(LocalStorage::new(root))
+            }
+        };
+        backend
+    }).await.clone()
+}
+
+/// Rejects keys that are empty, absolute or try to escape the storage root.
+pub fn validate_key(key: &str) -> Result<(), StorageError> {
+    if key.is_empty() || key.starts_with('/') || key.contains('\\') || key.split('/').any(|part| part == ".." || part == "." || part.is_empty()) {
+        return Err(StorageError::InvalidKey(key.to_string()))
+    }
+    Ok(())
+}